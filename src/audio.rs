@@ -59,6 +59,8 @@ fn update_music_volume(
 pub enum AudioEvent {
     Jump,
     LevelComplete,
+    Splash,
+    Spring,
 }
 
 fn sound_events(
@@ -91,6 +93,30 @@ fn sound_events(
                     ..default()
                 });
             }
+            AudioEvent::Splash => {
+                commands.spawn(AudioBundle {
+                    source: asset_server.load("audio/plop.ogg"),
+                    settings: PlaybackSettings {
+                        volume: Volume::new(audio_state.volume),
+                        mode: PlaybackMode::Once,
+                        ..Default::default()
+                    },
+                    ..default()
+                });
+            }
+            AudioEvent::Spring => {
+                // a higher pitched jump sound, so springs and jumps can be told apart
+                commands.spawn(AudioBundle {
+                    source: asset_server.load("audio/jump.wav"),
+                    settings: PlaybackSettings {
+                        volume: Volume::new(audio_state.volume),
+                        mode: PlaybackMode::Once,
+                        speed: 1.5,
+                        ..Default::default()
+                    },
+                    ..default()
+                });
+            }
         }
     }
 }
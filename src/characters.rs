@@ -0,0 +1,423 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::colliders::player_collider;
+use crate::constants::sprites::{fox, FrameRange};
+use crate::ground_detection::{spawn_ground_sensor, GroundDetection, GroundSensor};
+use crate::input::PlayerAction;
+use crate::player::{player_movement, AnimationIndices, Player};
+
+/// Which frames of a character's sprite sheet to play in each situation.
+#[derive(Copy, Clone, Debug)]
+pub struct AnimationTable {
+    pub idle: FrameRange,
+    pub walk: FrameRange,
+    pub climb: FrameRange,
+    pub climb_idle: FrameRange,
+    pub push: FrameRange,
+    pub swim: FrameRange,
+    pub swim_idle: FrameRange,
+    pub jump_up: FrameRange,
+    pub jump_down: FrameRange,
+}
+
+impl AnimationTable {
+    /// For sprite sheets with a single frame.
+    const fn still(frame: usize) -> AnimationTable {
+        let range = FrameRange {
+            first: frame,
+            last: frame,
+        };
+        AnimationTable {
+            idle: range,
+            walk: range,
+            climb: range,
+            climb_idle: range,
+            push: range,
+            swim: range,
+            swim_idle: range,
+            jump_up: range,
+            jump_down: range,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MovementStats {
+    pub run_speed: f32,
+    pub jump_velocity: f32,
+    pub climb_speed: f32,
+}
+
+/// Everything that makes a playable character look and move the way it does.
+#[derive(Copy, Clone, Debug)]
+pub struct Character {
+    pub name: &'static str,
+    pub sprite_sheet: &'static str,
+    pub frame_size: UVec2,
+    pub columns: u32,
+    pub rows: u32,
+    /// Space between the frames of the sprite sheet
+    pub padding: Option<UVec2>,
+    /// Size to draw frames at, if it differs from `frame_size`
+    pub display_size: Option<Vec2>,
+    pub animations: AnimationTable,
+    pub collider_half_extents: Vec2,
+    pub stats: MovementStats,
+}
+
+pub const CHARACTERS: [Character; 3] = [
+    Character {
+        name: "Fox",
+        sprite_sheet: "images/fox_sprite_sheet.png",
+        frame_size: UVec2::splat(32),
+        columns: 6,
+        rows: 6,
+        padding: Some(UVec2::new(1, 0)),
+        display_size: None,
+        animations: AnimationTable {
+            idle: fox::IDLE_FRAMES,
+            walk: fox::WALK_FRAMES,
+            climb: fox::CLIMB_FRAMES,
+            climb_idle: fox::CLIMB_FRAMES_IDLE,
+            // the sheet has no pushing frames, so pushing plays the walk cycle
+            push: fox::WALK_FRAMES,
+            // the sheet has no swimming frames either, so swimming plays the sitting ones of row 3
+            swim: fox::SWIM_FRAMES,
+            swim_idle: fox::SWIM_FRAMES_IDLE,
+            jump_up: fox::JUMP_UP_FRAMES,
+            jump_down: fox::JUMP_DOWN_FRAMES,
+        },
+        collider_half_extents: Vec2::new(10., 16.),
+        stats: MovementStats {
+            run_speed: 200.,
+            jump_velocity: 400.,
+            climb_speed: 200.,
+        },
+    },
+    Character {
+        name: "Ghost",
+        sprite_sheet: "images/ghost.png",
+        frame_size: UVec2::splat(32),
+        columns: 1,
+        rows: 1,
+        padding: None,
+        display_size: None,
+        animations: AnimationTable::still(0),
+        collider_half_extents: Vec2::new(10., 12.),
+        stats: MovementStats {
+            run_speed: 160.,
+            jump_velocity: 450.,
+            climb_speed: 250.,
+        },
+    },
+    Character {
+        name: "Stick",
+        sprite_sheet: "images/player.png",
+        frame_size: UVec2::splat(64),
+        columns: 1,
+        rows: 1,
+        padding: None,
+        display_size: Some(Vec2::splat(32.)),
+        animations: AnimationTable::still(0),
+        collider_half_extents: Vec2::new(8., 16.),
+        stats: MovementStats {
+            run_speed: 240.,
+            jump_velocity: 380.,
+            climb_speed: 180.,
+        },
+    },
+];
+
+/// The character a player is playing as.
+#[derive(Copy, Clone, Debug, Component)]
+pub struct PlayerCharacter(pub &'static Character);
+
+impl Default for PlayerCharacter {
+    fn default() -> Self {
+        PlayerCharacter(&CHARACTERS[0])
+    }
+}
+
+/// Index into `CHARACTERS` of the character picked on the selection screen.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Resource)]
+pub struct SelectedCharacter(pub usize);
+
+/// The selection screen is shown on startup and reopened with Tab.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Resource)]
+pub struct CharacterSelect {
+    pub open: bool,
+    highlighted: usize,
+}
+
+impl Default for CharacterSelect {
+    fn default() -> Self {
+        CharacterSelect {
+            open: true,
+            highlighted: 0,
+        }
+    }
+}
+
+pub fn character_select_closed(character_select: Res<CharacterSelect>) -> bool {
+    !character_select.open
+}
+
+/// The sprite sheet and atlas layout of every entry in `CHARACTERS`, in the same order.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct CharacterAssets(pub Vec<(Handle<Image>, Handle<TextureAtlasLayout>)>);
+
+#[derive(Component)]
+struct CharacterSelectScreen;
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.insert_resource(CharacterAssets(
+        CHARACTERS
+            .iter()
+            .map(|character| {
+                (
+                    asset_server.load(character.sprite_sheet),
+                    layouts.add(TextureAtlasLayout::from_grid(
+                        character.frame_size,
+                        character.columns,
+                        character.rows,
+                        character.padding,
+                        None,
+                    )),
+                )
+            })
+            .collect(),
+    ));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.),
+                ..default()
+            },
+            background_color: Color::srgba(0., 0., 0., 0.7).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(CharacterSelectScreen);
+}
+
+fn open_character_select(
+    mut character_select: ResMut<CharacterSelect>,
+    selected_character: Res<SelectedCharacter>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    if !character_select.open && keyboard_input.just_pressed(KeyCode::Tab) {
+        character_select.open = true;
+        character_select.highlighted = selected_character.0;
+    }
+}
+
+fn navigate_character_select(
+    mut character_select: ResMut<CharacterSelect>,
+    mut selected_character: ResMut<SelectedCharacter>,
+    input: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    if !character_select.open {
+        return;
+    }
+
+    // every local player can drive the selection
+    let just_pressed = |action| input.iter().any(|input| input.just_pressed(&action));
+
+    let count = CHARACTERS.len();
+    if just_pressed(PlayerAction::Left) {
+        character_select.highlighted = (character_select.highlighted + count - 1) % count;
+    }
+    if just_pressed(PlayerAction::Right) {
+        character_select.highlighted = (character_select.highlighted + 1) % count;
+    }
+    if just_pressed(PlayerAction::Jump) {
+        character_select.open = false;
+        if selected_character.0 != character_select.highlighted {
+            selected_character.0 = character_select.highlighted;
+        }
+    }
+}
+
+fn update_character_select_screen(
+    mut commands: Commands,
+    character_select: Res<CharacterSelect>,
+    character_assets: Res<CharacterAssets>,
+    mut screen: Query<(Entity, &mut Visibility), With<CharacterSelectScreen>>,
+) {
+    if !character_select.is_changed() {
+        return;
+    }
+    let Ok((screen, mut visibility)) = screen.get_single_mut() else {
+        return;
+    };
+
+    *visibility = if character_select.open {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    commands
+        .entity(screen)
+        .despawn_descendants()
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                "Choose your character",
+                TextStyle {
+                    font_size: 32.,
+                    ..default()
+                },
+            ));
+
+            screen
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(24.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (index, (character, (texture, layout))) in
+                        CHARACTERS.iter().zip(&character_assets.0).enumerate()
+                    {
+                        let highlighted = index == character_select.highlighted;
+                        row.spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                padding: UiRect::all(Val::Px(8.)),
+                                row_gap: Val::Px(8.),
+                                ..default()
+                            },
+                            background_color: if highlighted {
+                                Color::srgba(1., 1., 1., 0.2).into()
+                            } else {
+                                Color::NONE.into()
+                            },
+                            ..default()
+                        })
+                        .with_children(|entry| {
+                            entry
+                                .spawn(ImageBundle {
+                                    style: Style {
+                                        width: Val::Px(64.),
+                                        height: Val::Px(64.),
+                                        ..default()
+                                    },
+                                    image: UiImage::new(texture.clone()),
+                                    ..default()
+                                })
+                                .insert(TextureAtlas {
+                                    layout: layout.clone(),
+                                    index: character.animations.idle.first,
+                                });
+                            entry.spawn(TextBundle::from_section(
+                                character.name,
+                                TextStyle {
+                                    font_size: 20.,
+                                    color: if highlighted {
+                                        Color::srgb(0.95, 0.8, 0.2)
+                                    } else {
+                                        Color::WHITE
+                                    },
+                                    ..default()
+                                },
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+/// Swaps the sprite sheet, collider and ground sensor of players over to the selected character.
+#[allow(clippy::type_complexity)]
+fn apply_character(
+    selected_character: Res<SelectedCharacter>,
+    character_assets: Res<CharacterAssets>,
+    mut players: Query<
+        (
+            Entity,
+            &mut PlayerCharacter,
+            &mut Handle<Image>,
+            &mut TextureAtlas,
+            &mut Sprite,
+            &mut Collider,
+            &mut AnimationIndices,
+        ),
+        (With<GroundDetection>, Without<GroundSensor>),
+    >,
+    added_players: Query<(), Added<PlayerCharacter>>,
+    mut ground_sensors: Query<
+        (&GroundSensor, &mut Transform, &mut Collider),
+        Without<GroundDetection>,
+    >,
+) {
+    let character = &CHARACTERS[selected_character.0];
+    let (texture, layout) = &character_assets.0[selected_character.0];
+
+    for (
+        entity,
+        mut player_character,
+        mut player_texture,
+        mut atlas,
+        mut sprite,
+        mut collider,
+        mut animation_indices,
+    ) in &mut players
+    {
+        if !selected_character.is_changed() && !added_players.contains(entity) {
+            continue;
+        }
+
+        player_character.0 = character;
+        *player_texture = texture.clone();
+        atlas.layout = layout.clone();
+        atlas.index = character.animations.idle.first;
+        animation_indices.set(character.animations.idle);
+        sprite.custom_size = character.display_size;
+
+        let half_extents = character.collider_half_extents;
+        *collider = player_collider(half_extents);
+
+        // the sensor stays on whichever side gravity has put it
+        for (sensor, mut transform, mut sensor_collider) in &mut ground_sensors {
+            if sensor.ground_detection_entity == entity {
+                transform.translation.y = half_extents.y.copysign(transform.translation.y);
+                *sensor_collider = Collider::cuboid(half_extents.x / 2., 2.);
+            }
+        }
+    }
+}
+
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedCharacter>()
+            .init_resource::<CharacterSelect>()
+            .add_systems(Startup, setup)
+            .add_systems(Update, open_character_select)
+            // after movement, so the jump that confirms a character doesn't also make them jump
+            .add_systems(
+                FixedUpdate,
+                navigate_character_select.after(player_movement),
+            )
+            .add_systems(Update, update_character_select_screen)
+            // before the ground sensor is made, so it is made to fit the character
+            .add_systems(Update, apply_character.before(spawn_ground_sensor));
+    }
+}
@@ -3,7 +3,9 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::colliders::SensorBundle;
-use crate::constants::IntGridValues;
+use crate::constants::{IntGridValues, TILE_SIZE};
+use crate::one_way_platforms::OneWayPlatform;
+use crate::SimulationSet;
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Climber {
@@ -14,6 +16,11 @@ pub struct Climber {
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Climbable;
 
+/// Marks the colliders spawned on the topmost tile of a ladder:
+/// a one-way platform to stand on, and a sensor to start climbing down from.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct LadderTop;
+
 #[derive(Clone, Default, Bundle, LdtkIntCell)]
 pub struct LadderBundle {
     #[from_int_grid_cell]
@@ -57,6 +64,96 @@ pub fn detect_climb_range(
     }
 }
 
+pub fn spawn_ladder_tops(
+    mut commands: Commands,
+    added_ladders: Query<(Entity, &GridCoords, &Parent), Added<Climbable>>,
+    ladders: Query<(&GridCoords, &Parent), With<Climbable>>,
+) {
+    if added_ladders.is_empty() {
+        return;
+    }
+
+    let ladder_locations: HashSet<(Entity, GridCoords)> = ladders
+        .iter()
+        .map(|(&grid_coords, parent)| (parent.get(), grid_coords))
+        .collect();
+
+    let half_tile = TILE_SIZE as f32 / 2.;
+
+    for (entity, grid_coords, parent) in &added_ladders {
+        let above = GridCoords::new(grid_coords.x, grid_coords.y + 1);
+        if ladder_locations.contains(&(parent.get(), above)) {
+            continue;
+        }
+
+        commands.entity(entity).with_children(|ladder| {
+            ladder
+                .spawn_empty()
+                .insert(Collider::cuboid(half_tile, half_tile))
+                .insert(OneWayPlatform)
+                .insert(ActiveHooks::MODIFY_SOLVER_CONTACTS)
+                .insert(LadderTop)
+                .insert(Transform::default())
+                .insert(GlobalTransform::default());
+
+            // Reaches a little above the ladder so that climbers standing on top are in range
+            ladder
+                .spawn_empty()
+                .insert(Collider::cuboid(half_tile, 4.))
+                .insert(Sensor)
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                .insert(Climbable)
+                .insert(LadderTop)
+                .insert(Transform::from_xyz(0., half_tile, 0.))
+                .insert(GlobalTransform::default());
+        });
+    }
+}
+
+/// Keeps climbers centred on the ladder they are climbing.
+pub fn snap_to_ladder(
+    mut climbers: Query<(&Climber, &mut Transform, &GlobalTransform)>,
+    climbables: Query<&GlobalTransform, With<Climbable>>,
+) {
+    for (climber, mut transform, global_transform) in &mut climbers {
+        if !climber.climbing {
+            continue;
+        }
+
+        let x = global_transform.translation().x;
+        let nearest_ladder_x = climber
+            .intersecting_climbables
+            .iter()
+            .filter_map(|climbable| climbables.get(*climbable).ok())
+            .map(|ladder_transform| ladder_transform.translation().x)
+            .min_by(|a, b| (a - x).abs().total_cmp(&(b - x).abs()));
+
+        if let Some(ladder_x) = nearest_ladder_x {
+            transform.translation.x += ladder_x - x;
+        }
+    }
+}
+
+/// Stops climbing once a climber moving up has left the ladder itself
+/// and is only touching the area above its top, so they land on it.
+pub fn dismount_at_ladder_top(
+    mut climbers: Query<(&mut Climber, &mut Velocity)>,
+    ladder_tops: Query<(), With<LadderTop>>,
+) {
+    for (mut climber, mut velocity) in &mut climbers {
+        if climber.climbing
+            && velocity.linvel.y > 0.
+            && climber
+                .intersecting_climbables
+                .iter()
+                .all(|climbable| ladder_tops.contains(*climbable))
+        {
+            climber.climbing = false;
+            velocity.linvel.y = 0.;
+        }
+    }
+}
+
 pub fn ignore_gravity_if_climbing(
     mut query: Query<(&Climber, &mut GravityScale), Changed<Climber>>,
 ) {
@@ -73,8 +170,14 @@ pub struct ClimbingPlugin;
 
 impl Plugin for ClimbingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, detect_climb_range)
-            .add_systems(Update, ignore_gravity_if_climbing)
+        app.add_systems(FixedUpdate, detect_climb_range.in_set(SimulationSet))
+            .add_systems(Update, spawn_ladder_tops)
+            .add_systems(FixedUpdate, snap_to_ladder.in_set(SimulationSet))
+            .add_systems(FixedUpdate, dismount_at_ladder_top.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                ignore_gravity_if_climbing.in_set(SimulationSet),
+            )
             .register_ldtk_int_cell::<LadderBundle>(IntGridValues::Ladder as i32);
     }
 }
@@ -4,6 +4,19 @@ use bevy_ecs_ldtk::prelude::*;
 use crate::constants::IntGridValues;
 use bevy_rapier2d::prelude::*;
 
+/// The player's corners are rounded off by this much, so they ride up small steps,
+/// like where a gentle slope meets the next tile, instead of snagging on them.
+pub const PLAYER_CORNER_RADIUS: f32 = 4.;
+
+/// The player's collider, with `PLAYER_CORNER_RADIUS` rounded corners inside the half extents.
+pub fn player_collider(half_extents: Vec2) -> Collider {
+    Collider::round_cuboid(
+        half_extents.x - PLAYER_CORNER_RADIUS,
+        half_extents.y - PLAYER_CORNER_RADIUS,
+        PLAYER_CORNER_RADIUS,
+    )
+}
+
 #[derive(Clone, Default, Bundle, LdtkIntCell)]
 pub struct ColliderBundle {
     pub collider: Collider,
@@ -12,6 +25,7 @@ pub struct ColliderBundle {
     pub rotation_constraints: LockedAxes,
     pub gravity_scale: GravityScale,
     pub friction: Friction,
+    pub damping: Damping,
     pub density: ColliderMassProperties,
 }
 
@@ -21,7 +35,7 @@ impl From<&EntityInstance> for ColliderBundle {
 
         match entity_instance.identifier.as_ref() {
             "Player" => ColliderBundle {
-                collider: Collider::cuboid(10., 16.),
+                collider: player_collider(Vec2::new(10., 16.)),
                 rigid_body: RigidBody::Dynamic,
                 friction: Friction {
                     coefficient: 0.0,
@@ -30,6 +44,50 @@ impl From<&EntityInstance> for ColliderBundle {
                 rotation_constraints,
                 ..Default::default()
             },
+            "MovingPlatform" => ColliderBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                rigid_body: RigidBody::KinematicVelocityBased,
+                friction: Friction::new(1.0),
+                rotation_constraints,
+                ..Default::default()
+            },
+            "Crate" => ColliderBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                rigid_body: RigidBody::Dynamic,
+                friction: Friction::new(0.5),
+                density: ColliderMassProperties::Density(2.0),
+                rotation_constraints,
+                ..Default::default()
+            },
+            "Enemy" => ColliderBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                rigid_body: RigidBody::Dynamic,
+                friction: Friction {
+                    coefficient: 0.0,
+                    combine_rule: CoefficientCombineRule::Min,
+                },
+                rotation_constraints,
+                ..Default::default()
+            },
+            "Door" | "LockedDoor" | "Turret" => ColliderBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                rigid_body: RigidBody::Fixed,
+                friction: Friction::new(1.0),
+                rotation_constraints,
+                ..Default::default()
+            },
             _ => ColliderBundle::default(),
         }
     }
@@ -58,3 +116,23 @@ impl From<IntGridCell> for SensorBundle {
         }
     }
 }
+
+impl From<&EntityInstance> for SensorBundle {
+    fn from(entity_instance: &EntityInstance) -> SensorBundle {
+        let rotation_constraints = LockedAxes::ROTATION_LOCKED;
+
+        match entity_instance.identifier.as_ref() {
+            "Button" | "PressurePlate" | "Lever" | "Key" | "Switch" | "Spring" | "Teleporter"
+            | "GravityZone" | "WindZone" => SensorBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                sensor: Sensor,
+                rotation_constraints,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+            },
+            _ => SensorBundle::default(),
+        }
+    }
+}
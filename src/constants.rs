@@ -9,6 +9,20 @@ pub enum IntGridValues {
     Stone = 3,
     Goal = 4,
     SpawnPoint = 5,
+    OneWay = 6,
+    Water = 7,
+    Ice = 8,
+    SlopeUpRight = 9,
+    SlopeUpLeft = 10,
+    GentleSlopeUpRightLow = 11,
+    GentleSlopeUpRightHigh = 12,
+    GentleSlopeUpLeftLow = 13,
+    GentleSlopeUpLeftHigh = 14,
+    OnBlock = 15,
+    OffBlock = 16,
+    ConveyorLeft = 17,
+    ConveyorRight = 18,
+    Crumble = 19,
 }
 
 impl From<i32> for IntGridValues {
@@ -19,12 +33,27 @@ impl From<i32> for IntGridValues {
             3 => IntGridValues::Stone,
             4 => IntGridValues::Goal,
             5 => IntGridValues::SpawnPoint,
+            6 => IntGridValues::OneWay,
+            7 => IntGridValues::Water,
+            8 => IntGridValues::Ice,
+            9 => IntGridValues::SlopeUpRight,
+            10 => IntGridValues::SlopeUpLeft,
+            11 => IntGridValues::GentleSlopeUpRightLow,
+            12 => IntGridValues::GentleSlopeUpRightHigh,
+            13 => IntGridValues::GentleSlopeUpLeftLow,
+            14 => IntGridValues::GentleSlopeUpLeftHigh,
+            15 => IntGridValues::OnBlock,
+            16 => IntGridValues::OffBlock,
+            17 => IntGridValues::ConveyorLeft,
+            18 => IntGridValues::ConveyorRight,
+            19 => IntGridValues::Crumble,
             _ => IntGridValues::Dirt,
         }
     }
 }
 
 pub mod sprites {
+    #[derive(Copy, Clone, Debug)]
     pub struct FrameRange {
         pub first: usize,
         pub last: usize,
@@ -42,6 +71,14 @@ pub mod sprites {
             first: 12,
             last: 12,
         };
+        pub const SWIM_FRAMES: FrameRange = FrameRange {
+            first: 18,
+            last: 20,
+        };
+        pub const SWIM_FRAMES_IDLE: FrameRange = FrameRange {
+            first: 18,
+            last: 18,
+        };
         pub const _DEATH_FRAMES: FrameRange = FrameRange {
             first: 24,
             last: 25,
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::constants::TILE_SIZE;
+use crate::ground_detection::GroundDetection;
+use crate::utils::move_towards;
+use crate::SimulationSet;
+
+pub const CONVEYOR_SPEED: f32 = 60.;
+/// How quickly a belt drags bodies without their own movement, like crates, up to its speed.
+pub const CONVEYOR_ACCELERATION: f32 = 600.;
+pub const CONVEYOR_FRAME_COUNT: usize = 4;
+/// Each frame of `images/conveyor.png` moves the belt by a quarter of a tile.
+pub const CONVEYOR_FRAME_SECONDS: f32 = TILE_SIZE as f32 / 4. / CONVEYOR_SPEED;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum ConveyorDirection {
+    #[default]
+    Left,
+    Right,
+}
+
+/// A merged group of conveyor tiles, spawned by the `WallPlugin`.
+#[derive(Clone, Debug, Default, Component)]
+pub struct ConveyorBelt {
+    pub direction: ConveyorDirection,
+    frame: usize,
+    frame_timer: Timer,
+}
+
+impl From<ConveyorDirection> for ConveyorBelt {
+    fn from(direction: ConveyorDirection) -> ConveyorBelt {
+        ConveyorBelt {
+            direction,
+            frame: 0,
+            frame_timer: Timer::from_seconds(CONVEYOR_FRAME_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+impl ConveyorBelt {
+    /// The velocity given to anything resting on the belt.
+    pub fn surface_velocity(&self) -> Vec2 {
+        match self.direction {
+            ConveyorDirection::Left => Vec2::new(-CONVEYOR_SPEED, 0.),
+            ConveyorDirection::Right => Vec2::new(CONVEYOR_SPEED, 0.),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct ConveyorSprite;
+
+#[derive(Clone, Debug, Resource)]
+pub struct ConveyorAssets {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.insert_resource(ConveyorAssets {
+        texture: asset_server.load("images/conveyor.png"),
+        layout: layouts.add(TextureAtlasLayout::from_grid(
+            UVec2::splat(TILE_SIZE as u32),
+            CONVEYOR_FRAME_COUNT as u32,
+            1,
+            None,
+            None,
+        )),
+    });
+}
+
+/// The IntGrid layer has no tiles of its own, so every tile of a belt gets an animated sprite.
+pub fn spawn_conveyor_sprites(
+    mut commands: Commands,
+    conveyor_assets: Res<ConveyorAssets>,
+    belts: Query<(Entity, &Collider), Added<ConveyorBelt>>,
+) {
+    for (entity, collider) in &belts {
+        let Some(cuboid) = collider.as_cuboid() else {
+            continue;
+        };
+        let half_extents = cuboid.half_extents();
+        let columns = (half_extents.x * 2. / TILE_SIZE as f32).round() as i32;
+        let rows = (half_extents.y * 2. / TILE_SIZE as f32).round() as i32;
+
+        commands.entity(entity).with_children(|belt| {
+            for row in 0..rows {
+                for column in 0..columns {
+                    let translation = Vec3::new(
+                        (column as f32 + 0.5) * TILE_SIZE as f32 - half_extents.x,
+                        (row as f32 + 0.5) * TILE_SIZE as f32 - half_extents.y,
+                        0.,
+                    );
+                    belt.spawn(SpriteBundle {
+                        texture: conveyor_assets.texture.clone(),
+                        transform: Transform::from_translation(translation),
+                        ..Default::default()
+                    })
+                    .insert(TextureAtlas {
+                        layout: conveyor_assets.layout.clone(),
+                        index: 0,
+                    })
+                    .insert(ConveyorSprite);
+                }
+            }
+        });
+    }
+}
+
+pub fn animate_conveyors(
+    mut belts: Query<(&mut ConveyorBelt, &Children)>,
+    mut sprites: Query<&mut TextureAtlas, With<ConveyorSprite>>,
+    time: Res<Time>,
+) {
+    for (mut belt, children) in &mut belts {
+        if !belt.frame_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // the sprite sheet scrolls rightwards, so left belts play it backwards
+        belt.frame = match belt.direction {
+            ConveyorDirection::Left => {
+                (belt.frame + CONVEYOR_FRAME_COUNT - 1) % CONVEYOR_FRAME_COUNT
+            }
+            ConveyorDirection::Right => (belt.frame + 1) % CONVEYOR_FRAME_COUNT,
+        };
+
+        let mut children_sprites = sprites.iter_many_mut(children.iter());
+        while let Some(mut atlas) = children_sprites.fetch_next() {
+            atlas.index = belt.frame;
+        }
+    }
+}
+
+/// Bodies with ground detection add the surface velocity to their own movement instead.
+pub fn carry_bodies_on_conveyors(
+    rapier_context: Res<RapierContext>,
+    belts: Query<(Entity, &ConveyorBelt, &Collider, &GlobalTransform)>,
+    mut bodies: Query<(&RigidBody, &GlobalTransform, &mut Velocity), Without<GroundDetection>>,
+    time: Res<Time>,
+) {
+    for (belt_entity, belt, collider, belt_transform) in &belts {
+        let Some(cuboid) = collider.as_cuboid() else {
+            continue;
+        };
+        let belt_top = belt_transform.translation().y + cuboid.half_extents().y;
+
+        for contact_pair in rapier_context.contact_pairs_with(belt_entity) {
+            if !contact_pair.has_any_active_contacts() {
+                continue;
+            }
+
+            let other = if contact_pair.collider1() == belt_entity {
+                contact_pair.collider2()
+            } else {
+                contact_pair.collider1()
+            };
+
+            let Ok((RigidBody::Dynamic, transform, mut velocity)) = bodies.get_mut(other) else {
+                continue;
+            };
+            // bodies touching the side or the underside of the belt are not carried
+            if transform.translation().y < belt_top {
+                continue;
+            }
+
+            velocity.linvel.x = move_towards(
+                velocity.linvel.x,
+                belt.surface_velocity().x,
+                CONVEYOR_ACCELERATION * time.delta_seconds(),
+            );
+        }
+    }
+}
+
+pub struct ConveyorPlugin;
+
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, spawn_conveyor_sprites)
+            .add_systems(Update, animate_conveyors)
+            .add_systems(FixedUpdate, carry_bodies_on_conveyors.in_set(SimulationSet));
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::ColliderBundle;
+use crate::game_flow::RestartLevel;
+use crate::gravity::Gravity;
+use crate::rewind::Rewindable;
+use crate::wind::WindsweptBundle;
+use crate::SimulationSet;
+
+pub const PUSH_SPEED_FACTOR: f32 = 0.5;
+
+/// A dynamic box that can be pushed around, stacked and stood on.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct Crate {
+    spawn_translation: Vec3,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CrateBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    pub crate_: Crate,
+    pub gravity: Gravity,
+    pub windswept: WindsweptBundle,
+    pub rewindable: Rewindable,
+}
+
+/// Tracks crates resting against either side of an entity, so it can push them.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Pusher {
+    pub crate_on_left: bool,
+    pub crate_on_right: bool,
+    pub pushing: bool,
+}
+
+fn record_crate_spawns(mut crates: Query<(&mut Crate, &Transform), Added<Crate>>) {
+    for (mut crate_, transform) in &mut crates {
+        crate_.spawn_translation = transform.translation;
+    }
+}
+
+pub fn detect_crates_beside_pushers(
+    rapier_context: Res<RapierContext>,
+    mut pushers: Query<(Entity, &GlobalTransform, &mut Pusher)>,
+    crates: Query<&GlobalTransform, With<Crate>>,
+) {
+    for (entity, pusher_transform, mut pusher) in &mut pushers {
+        let mut crate_on_left = false;
+        let mut crate_on_right = false;
+
+        for contact_pair in rapier_context.contact_pairs_with(entity) {
+            if !contact_pair.has_any_active_contacts() {
+                continue;
+            }
+
+            let other = if contact_pair.collider1() == entity {
+                contact_pair.collider2()
+            } else {
+                contact_pair.collider1()
+            };
+
+            if let Ok(crate_transform) = crates.get(other) {
+                let offset = crate_transform.translation() - pusher_transform.translation();
+                // crates under or on top of the pusher are not beside it
+                if offset.x.abs() > offset.y.abs() {
+                    crate_on_left |= offset.x < 0.;
+                    crate_on_right |= offset.x > 0.;
+                }
+            }
+        }
+
+        pusher.crate_on_left = crate_on_left;
+        pusher.crate_on_right = crate_on_right;
+    }
+}
+
+fn reset_crates(
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut crates: Query<(&Crate, &mut Transform, &mut Velocity)>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for (crate_, mut transform, mut velocity) in &mut crates {
+        transform.translation = crate_.spawn_translation;
+        *velocity = Velocity::zero();
+    }
+}
+
+pub struct CratePlugin;
+
+impl Plugin for CratePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CrateBundle>("Crate")
+            .add_systems(Update, record_crate_spawns)
+            .add_systems(
+                FixedUpdate,
+                detect_crates_beside_pushers.in_set(SimulationSet),
+            )
+            .add_systems(Update, reset_crates);
+    }
+}
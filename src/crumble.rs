@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::constants::TILE_SIZE;
+use crate::game_flow::RestartLevel;
+use crate::ground_detection::GroundSensor;
+use crate::player::Player;
+use crate::SimulationSet;
+
+/// How long a crumble tile shakes under a player before it falls away.
+pub const CRUMBLE_DELAY_SECONDS: f32 = 0.5;
+/// How long a fallen crumble tile stays gone before it comes back.
+pub const CRUMBLE_RESPAWN_SECONDS: f32 = 3.0;
+pub const CRUMBLE_SHAKE_DISTANCE: f32 = 1.0;
+pub const CRUMBLE_SHAKE_FREQUENCY: f32 = 40.;
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub enum CrumbleState {
+    #[default]
+    Intact,
+    Crumbling(Timer),
+    Collapsed(Timer),
+}
+
+/// A single crumble tile, spawned by the `WallPlugin` with a collider of its own.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct CrumbleTile {
+    pub state: CrumbleState,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct CrumbleSprite;
+
+/// The sprite is a child so it can shake without moving the collider under the player.
+pub fn spawn_crumble_sprites(mut commands: Commands, tiles: Query<Entity, Added<CrumbleTile>>) {
+    for entity in &tiles {
+        commands.entity(entity).with_children(|tile| {
+            tile.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(0.6, 0.45, 0.3),
+                    custom_size: Some(Vec2::splat(TILE_SIZE as f32)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(CrumbleSprite);
+        });
+    }
+}
+
+pub fn start_crumbling(
+    ground_sensors: Query<&GroundSensor>,
+    players: Query<(), With<Player>>,
+    mut tiles: Query<&mut CrumbleTile>,
+) {
+    for sensor in &ground_sensors {
+        if !players.contains(sensor.ground_detection_entity) {
+            continue;
+        }
+
+        for entity in &sensor.intersecting_ground_entities {
+            if let Ok(mut tile) = tiles.get_mut(*entity) {
+                if tile.state == CrumbleState::Intact {
+                    tile.state = CrumbleState::Crumbling(Timer::from_seconds(
+                        CRUMBLE_DELAY_SECONDS,
+                        TimerMode::Once,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+pub fn update_crumble_tiles(
+    mut commands: Commands,
+    mut tiles: Query<(Entity, &mut CrumbleTile, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut tile, mut visibility) in &mut tiles {
+        match &mut tile.state {
+            CrumbleState::Intact => (),
+            CrumbleState::Crumbling(timer) => {
+                if timer.tick(time.delta()).finished() {
+                    tile.state = CrumbleState::Collapsed(Timer::from_seconds(
+                        CRUMBLE_RESPAWN_SECONDS,
+                        TimerMode::Once,
+                    ));
+                    commands.entity(entity).insert(ColliderDisabled);
+                    *visibility = Visibility::Hidden;
+                }
+            }
+            CrumbleState::Collapsed(timer) => {
+                if timer.tick(time.delta()).finished() {
+                    tile.state = CrumbleState::Intact;
+                    commands.entity(entity).remove::<ColliderDisabled>();
+                    *visibility = Visibility::Inherited;
+                }
+            }
+        }
+    }
+}
+
+pub fn shake_crumble_sprites(
+    tiles: Query<&CrumbleTile>,
+    mut sprites: Query<(&Parent, &mut Transform), With<CrumbleSprite>>,
+    time: Res<Time>,
+) {
+    for (parent, mut transform) in &mut sprites {
+        let Ok(tile) = tiles.get(parent.get()) else {
+            continue;
+        };
+
+        transform.translation.x = match tile.state {
+            CrumbleState::Crumbling(_) => {
+                (time.elapsed_seconds() * CRUMBLE_SHAKE_FREQUENCY).sin() * CRUMBLE_SHAKE_DISTANCE
+            }
+            _ => 0.,
+        };
+    }
+}
+
+fn reset_crumble_tiles(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut tiles: Query<(Entity, &mut CrumbleTile, &mut Visibility)>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for (entity, mut tile, mut visibility) in &mut tiles {
+        tile.state = CrumbleState::Intact;
+        commands.entity(entity).remove::<ColliderDisabled>();
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub struct CrumblePlugin;
+
+impl Plugin for CrumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_crumble_sprites)
+            .add_systems(FixedUpdate, start_crumbling.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                update_crumble_tiles
+                    .after(start_crumbling)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, shake_crumble_sprites)
+            .add_systems(Update, reset_crumble_tiles);
+    }
+}
@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::AudioEvent;
+use crate::colliders::ColliderBundle;
+use crate::game_flow::RestartLevel;
+use crate::gravity::Gravity;
+use crate::health::{apply_damage, DamageEvent};
+use crate::player::Player;
+use crate::rewind::Rewindable;
+use crate::SimulationSet;
+
+/// The upwards velocity a player bounces off a stomped enemy with.
+pub const STOMP_BOUNCE_VELOCITY: f32 = 300.;
+/// How far ahead of and below its feet an enemy looks for walls and ledges.
+pub const PATROL_LOOKAHEAD: f32 = 2.;
+
+/// Walks back and forth, turning around at walls and ledges.
+///
+/// The LDtk entity has these fields:
+/// - `speed`: Float, in pixels per second
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct Enemy {
+    pub speed: f32,
+    /// 1 when walking right, -1 when walking left
+    pub direction: f32,
+    pub defeated: bool,
+    spawn_translation: Option<Vec3>,
+}
+
+impl From<&EntityInstance> for Enemy {
+    fn from(entity_instance: &EntityInstance) -> Enemy {
+        Enemy {
+            speed: entity_instance
+                .get_float_field("speed")
+                .copied()
+                .unwrap_or(40.),
+            direction: 1.,
+            defeated: false,
+            spawn_translation: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct EnemyBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub enemy: Enemy,
+    pub rewindable: Rewindable,
+}
+
+/// Raycasts only hit fixed colliders, so enemies turn at the level's walls but not at crates or players.
+pub fn patrol(
+    rapier_context: Res<RapierContext>,
+    mut enemies: Query<(
+        &mut Enemy,
+        &Collider,
+        &GlobalTransform,
+        &Transform,
+        &mut Velocity,
+        &mut Sprite,
+    )>,
+) {
+    let walls = QueryFilter::only_fixed().exclude_sensors();
+
+    for (mut enemy, collider, global_transform, transform, mut velocity, mut sprite) in &mut enemies
+    {
+        enemy.spawn_translation.get_or_insert(transform.translation);
+        if enemy.defeated {
+            continue;
+        }
+        let Some(cuboid) = collider.as_cuboid() else {
+            continue;
+        };
+        let half_extents = cuboid.half_extents();
+        let position = global_transform.translation().truncate();
+        let forward = Vec2::new(enemy.direction, 0.);
+
+        // no ledges to look for while falling
+        let grounded = rapier_context
+            .cast_ray(
+                position,
+                Vec2::NEG_Y,
+                half_extents.y + PATROL_LOOKAHEAD,
+                true,
+                walls,
+            )
+            .is_some();
+
+        let wall_ahead = rapier_context
+            .cast_ray(
+                position,
+                forward,
+                half_extents.x + PATROL_LOOKAHEAD,
+                true,
+                walls,
+            )
+            .is_some();
+        let ground_ahead = rapier_context
+            .cast_ray(
+                position + forward * (half_extents.x + PATROL_LOOKAHEAD),
+                Vec2::NEG_Y,
+                half_extents.y + PATROL_LOOKAHEAD,
+                true,
+                walls,
+            )
+            .is_some();
+
+        if wall_ahead || (grounded && !ground_ahead) {
+            enemy.direction = -enemy.direction;
+        }
+
+        velocity.linvel.x = enemy.direction * enemy.speed;
+        sprite.flip_x = enemy.direction < 0.;
+    }
+}
+
+/// Players landing on top of an enemy defeat it, any other contact hurts the player.
+#[allow(clippy::type_complexity)]
+pub fn enemy_contacts(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &Collider,
+        &GlobalTransform,
+        &mut Visibility,
+    )>,
+    mut players: Query<(&GlobalTransform, &Gravity, &mut Velocity), (With<Player>, Without<Enemy>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut audio_event: EventWriter<AudioEvent>,
+) {
+    for (entity, mut enemy, collider, enemy_transform, mut visibility) in &mut enemies {
+        if enemy.defeated {
+            continue;
+        }
+        let Some(cuboid) = collider.as_cuboid() else {
+            continue;
+        };
+
+        for contact_pair in rapier_context.contact_pairs_with(entity) {
+            if !contact_pair.has_any_active_contacts() {
+                continue;
+            }
+
+            let other = if contact_pair.collider1() == entity {
+                contact_pair.collider2()
+            } else {
+                contact_pair.collider1()
+            };
+            let Ok((player_transform, gravity, mut velocity)) = players.get_mut(other) else {
+                continue;
+            };
+
+            let height_above_enemy =
+                (player_transform.translation().y - enemy_transform.translation().y) * gravity.up();
+            if height_above_enemy > cuboid.half_extents().y {
+                enemy.defeated = true;
+                commands.entity(entity).insert(ColliderDisabled);
+                *visibility = Visibility::Hidden;
+                velocity.linvel.y = STOMP_BOUNCE_VELOCITY * gravity.up();
+                audio_event.send(AudioEvent::Jump);
+                break;
+            }
+            damage_events.send(DamageEvent {
+                target: other,
+                amount: 1,
+                source: enemy_transform.translation().truncate(),
+            });
+        }
+    }
+}
+
+fn reset_enemies(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &mut Transform,
+        &mut Velocity,
+        &mut Visibility,
+    )>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for (entity, mut enemy, mut transform, mut velocity, mut visibility) in &mut enemies {
+        if let Some(spawn_translation) = enemy.spawn_translation {
+            transform.translation = spawn_translation;
+        }
+        enemy.defeated = false;
+        enemy.direction = 1.;
+        *velocity = Velocity::zero();
+        commands.entity(entity).remove::<ColliderDisabled>();
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<EnemyBundle>("Enemy")
+            .add_systems(FixedUpdate, patrol.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                enemy_contacts.before(apply_damage).in_set(SimulationSet),
+            )
+            .add_systems(Update, reset_enemies);
+    }
+}
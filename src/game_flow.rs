@@ -1,4 +1,7 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_ldtk::utils::int_grid_index_to_grid_coords;
 use bevy_rapier2d::prelude::*;
@@ -7,6 +10,7 @@ use crate::audio::AudioEvent;
 use crate::colliders::SensorBundle;
 use crate::constants::{IntGridValues, TILE_SIZE};
 use crate::player::Player;
+use crate::SimulationSet;
 
 #[derive(Resource, Clone)]
 pub struct GameState {
@@ -19,6 +23,28 @@ impl GameState {
     }
 }
 
+/// The `current_level` of the level with this iid, read from its `World_Level_N` identifier.
+pub fn level_index(ldtk_project: &LdtkProject, level_iid: &str) -> Option<usize> {
+    ldtk_project
+        .find_raw_level_by_level_selection(&LevelSelection::Iid(LevelIid::new(level_iid)))
+        .and_then(|level| level.identifier.strip_prefix("World_Level_"))
+        .and_then(|index| index.parse().ok())
+}
+
+/// Seeds everything random in the game, so a replay can reproduce it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Resource)]
+pub struct RngSeed(pub u32);
+
+impl Default for RngSeed {
+    fn default() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos())
+            .unwrap_or_default();
+        RngSeed(nanos)
+    }
+}
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -133,31 +159,56 @@ fn restart_level(
             "Expected exactly 1 spawn point per level"
         );
 
-        // set player position to spawn point
+        // set every player's position to the spawn point
         let spawn_point = coords[0];
-        if let Ok((mut transform, _player)) = player_query.get_single_mut() {
+        for (mut transform, _player) in &mut player_query {
             transform.translation.x = (spawn_point.x * TILE_SIZE + TILE_SIZE / 2) as f32;
             transform.translation.y = (spawn_point.y * TILE_SIZE + TILE_SIZE / 2) as f32;
         }
     }
 }
+/// The level is finished once every player is standing in a goal.
+///
+/// Goal tiles are separate sensors, so a player is in the goal while touching any of them.
 pub fn finish_level(
     players: Query<Entity, With<Player>>,
     goals: Query<Entity, With<Goal>>,
+    mut goals_touched: Local<HashMap<Entity, HashSet<Entity>>>,
     mut collisions: EventReader<CollisionEvent>,
     mut next_level: EventWriter<NextLevel>,
     mut audio_event: EventWriter<AudioEvent>,
 ) {
     for collision in collisions.read() {
-        if let CollisionEvent::Started(collider_a, collider_b, _) = collision {
-            if (goals.get(*collider_a).is_ok() && players.get(*collider_b).is_ok())
-                || (players.get(*collider_a).is_ok() && goals.get(*collider_b).is_ok())
-            {
-                next_level.send(NextLevel);
-                audio_event.send(AudioEvent::LevelComplete);
+        match collision {
+            CollisionEvent::Started(collider_a, collider_b, _) => {
+                for (player, goal) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if players.contains(*player) && goals.contains(*goal) {
+                        goals_touched.entry(*player).or_default().insert(*goal);
+                    }
+                }
+            }
+            CollisionEvent::Stopped(collider_a, collider_b, _) => {
+                for (player, goal) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if let Some(touched) = goals_touched.get_mut(player) {
+                        touched.remove(goal);
+                        if touched.is_empty() {
+                            goals_touched.remove(player);
+                        }
+                    }
+                }
             }
         }
     }
+
+    if !goals_touched.is_empty()
+        && players
+            .iter()
+            .all(|player| goals_touched.contains_key(&player))
+    {
+        goals_touched.clear();
+        next_level.send(NextLevel);
+        audio_event.send(AudioEvent::LevelComplete);
+    }
 }
 
 fn level_selection(
@@ -230,7 +281,8 @@ impl Plugin for GameFlowPlugin {
             .add_event::<NextLevel>()
             .add_systems(Update, restart_level)
             .add_event::<RestartLevel>()
-            .add_systems(Update, finish_level)
+            .init_resource::<RngSeed>()
+            .add_systems(FixedUpdate, finish_level.in_set(SimulationSet))
             .register_ldtk_int_cell::<GoalBundle>(IntGridValues::Goal as i32);
     }
 }
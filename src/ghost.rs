@@ -0,0 +1,371 @@
+use std::fmt;
+use std::fs;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::characters::{character_select_closed, CharacterAssets, SelectedCharacter, CHARACTERS};
+use crate::constants::sprites::FrameRange;
+use crate::game_flow::{GameState, NextLevel, RestartLevel};
+use crate::player::{AnimationIndices, Player};
+use crate::replay::{not_playing_replay, not_waiting_for_replay};
+use crate::rewind::is_rewinding;
+use crate::SimulationSet;
+
+const GHOST_HEADER: &str = "my-game ghost 2";
+pub const GHOST_DIRECTORY: &str = "ghosts";
+pub const GHOST_ALPHA: f32 = 0.4;
+
+/// Where the first player was and what they looked like during one fixed tick.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GhostFrame {
+    pub translation: Vec3,
+    pub animation_indices: AnimationIndices,
+    pub atlas_index: usize,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+/// A completed run of a level, one frame per fixed tick.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GhostRun {
+    /// The iid of the level, like replays, so bests survive levels being renamed or reordered
+    pub level_iid: String,
+    pub character: usize,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl fmt::Display for GhostRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{GHOST_HEADER}")?;
+        writeln!(f, "{} {}", self.level_iid, self.character)?;
+        for frame in &self.frames {
+            let FrameRange { first, last } = frame.animation_indices.frame_range();
+            writeln!(
+                f,
+                "{} {} {} {first} {last} {} {} {}",
+                frame.translation.x,
+                frame.translation.y,
+                frame.translation.z,
+                frame.atlas_index,
+                frame.flip_x as u8,
+                frame.flip_y as u8,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl GhostRun {
+    /// Reads the format written by `Display`. Unreadable runs are treated as missing.
+    pub fn parse(text: &str) -> Option<GhostRun> {
+        let mut lines = text.lines();
+        if lines.next()? != GHOST_HEADER {
+            return None;
+        }
+
+        let mut run_info = lines.next()?.split_whitespace();
+        let level_iid = run_info.next()?.to_string();
+        let character = run_info.next()?.parse().ok()?;
+        if character >= CHARACTERS.len() {
+            return None;
+        }
+
+        let frames = lines
+            .map(|line| {
+                let values: Vec<&str> = line.split_whitespace().collect();
+                let &[x, y, z, first, last, atlas_index, flip_x, flip_y] = values.as_slice() else {
+                    return None;
+                };
+                let mut animation_indices = AnimationIndices::default();
+                animation_indices.set(FrameRange {
+                    first: first.parse().ok()?,
+                    last: last.parse().ok()?,
+                });
+                Some(GhostFrame {
+                    translation: Vec3::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?),
+                    animation_indices,
+                    atlas_index: atlas_index.parse().ok()?,
+                    flip_x: flip_x == "1",
+                    flip_y: flip_y == "1",
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(GhostRun {
+            level_iid,
+            character,
+            frames,
+        })
+    }
+
+    fn path(level_iid: &str) -> String {
+        format!("{GHOST_DIRECTORY}/level-{level_iid}.ghost")
+    }
+}
+
+/// The fastest completion of each level, kept in `GHOST_DIRECTORY` between sessions.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct PersonalBests(pub HashMap<String, GhostRun>);
+
+/// The run in progress, which becomes the new personal best if it finishes faster.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct GhostRecorder(pub GhostRun);
+
+/// Toggled with F3.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Resource)]
+pub struct GhostSettings {
+    pub enabled: bool,
+}
+
+impl Default for GhostSettings {
+    fn default() -> Self {
+        GhostSettings { enabled: true }
+    }
+}
+
+/// A translucent replay of the personal best, with no collider so nothing can touch it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Ghost {
+    pub frame: usize,
+}
+
+fn load_personal_bests(mut personal_bests: ResMut<PersonalBests>) {
+    let Ok(entries) = fs::read_dir(GHOST_DIRECTORY) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Some(run) = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|text| GhostRun::parse(&text))
+        else {
+            warn!("ignoring unreadable ghost {}", entry.path().display());
+            continue;
+        };
+        personal_bests.0.insert(run.level_iid.clone(), run);
+    }
+}
+
+fn toggle_ghost(keyboard_input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<GhostSettings>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+/// Every restart or new level is a fresh attempt, raced against the best one so far.
+#[allow(clippy::too_many_arguments)]
+fn start_attempt(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut level_events: EventReader<LevelEvent>,
+    game_state: Res<GameState>,
+    selected_character: Res<SelectedCharacter>,
+    character_assets: Res<CharacterAssets>,
+    personal_bests: Res<PersonalBests>,
+    settings: Res<GhostSettings>,
+    mut recorder: ResMut<GhostRecorder>,
+    ghosts: Query<Entity, With<Ghost>>,
+    worlds: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    let restarted = restart_level_reader.read().count() > 0;
+    let spawned = level_events
+        .read()
+        .any(|event| matches!(event, LevelEvent::Spawned(_)));
+    if !restarted && !spawned {
+        return;
+    }
+
+    let Some(level) = worlds
+        .get_single()
+        .ok()
+        .and_then(|ldtk_handle| ldtk_project_assets.get(ldtk_handle))
+        .and_then(|ldtk_project| {
+            ldtk_project.find_raw_level_by_level_selection(&game_state.current_level_identifier())
+        })
+    else {
+        return;
+    };
+
+    recorder.0 = GhostRun {
+        level_iid: level.iid.clone(),
+        character: selected_character.0,
+        frames: Vec::new(),
+    };
+
+    for ghost in &ghosts {
+        commands.entity(ghost).despawn_recursive();
+    }
+
+    let Some(best) = personal_bests.0.get(&recorder.0.level_iid) else {
+        return;
+    };
+    let Some(first_frame) = best.frames.first() else {
+        return;
+    };
+    let (texture, layout) = &character_assets.0[best.character];
+
+    commands
+        .spawn(SpriteBundle {
+            texture: texture.clone(),
+            sprite: Sprite {
+                color: Color::srgba(1., 1., 1., GHOST_ALPHA),
+                custom_size: CHARACTERS[best.character].display_size,
+                ..Default::default()
+            },
+            // just behind the player
+            transform: Transform::from_translation(first_frame.translation - Vec3::Z * 0.1),
+            visibility: if settings.enabled {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            },
+            ..Default::default()
+        })
+        .insert(TextureAtlas {
+            layout: layout.clone(),
+            index: first_frame.atlas_index,
+        })
+        .insert(first_frame.animation_indices)
+        .insert(Ghost::default());
+}
+
+/// Played back replays aren't the player's own runs, so they never become a personal best.
+fn record_ghost_frames(
+    mut recorder: ResMut<GhostRecorder>,
+    players: Query<(
+        &Player,
+        &Transform,
+        &AnimationIndices,
+        &TextureAtlas,
+        &Sprite,
+    )>,
+) {
+    let Some((_, transform, animation_indices, atlas, sprite)) =
+        players.iter().find(|(player, ..)| player.index == 0)
+    else {
+        return;
+    };
+
+    recorder.0.frames.push(GhostFrame {
+        translation: transform.translation,
+        animation_indices: *animation_indices,
+        atlas_index: atlas.index,
+        flip_x: sprite.flip_x,
+        flip_y: sprite.flip_y,
+    });
+}
+
+/// Rewound ticks are taken back out of the run, and the ghost is held back to stay in step.
+fn rewind_ghosts(mut recorder: ResMut<GhostRecorder>, mut ghosts: Query<&mut Ghost>) {
+    recorder.0.frames.pop();
+    for mut ghost in &mut ghosts {
+        ghost.frame = ghost.frame.saturating_sub(1);
+    }
+}
+
+/// The ghost keeps pace with the player one tick at a time, and waits at the goal once done.
+fn play_ghosts(
+    mut ghosts: Query<(
+        &mut Ghost,
+        &mut Transform,
+        &mut AnimationIndices,
+        &mut TextureAtlas,
+        &mut Sprite,
+    )>,
+    recorder: Res<GhostRecorder>,
+    personal_bests: Res<PersonalBests>,
+) {
+    let Some(best) = personal_bests.0.get(&recorder.0.level_iid) else {
+        return;
+    };
+
+    for (mut ghost, mut transform, mut animation_indices, mut atlas, mut sprite) in &mut ghosts {
+        let Some(frame) = best.frames.get(ghost.frame) else {
+            continue;
+        };
+        ghost.frame += 1;
+
+        transform.translation = frame.translation - Vec3::Z * 0.1;
+        *animation_indices = frame.animation_indices;
+        atlas.index = frame.atlas_index;
+        sprite.flip_x = frame.flip_x;
+        sprite.flip_y = frame.flip_y;
+    }
+}
+
+fn show_ghosts(settings: Res<GhostSettings>, mut ghosts: Query<&mut Visibility, With<Ghost>>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut ghosts {
+        *visibility = if settings.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn save_personal_best(
+    mut next_level_reader: EventReader<NextLevel>,
+    mut recorder: ResMut<GhostRecorder>,
+    mut personal_bests: ResMut<PersonalBests>,
+) {
+    if next_level_reader.read().count() == 0 {
+        return;
+    }
+
+    let run = std::mem::take(&mut recorder.0);
+    if run.frames.is_empty() {
+        return;
+    }
+    if let Some(best) = personal_bests.0.get(&run.level_iid) {
+        if best.frames.len() <= run.frames.len() {
+            return;
+        }
+    }
+
+    info!("new personal best on level {}", run.level_iid);
+    let path = GhostRun::path(&run.level_iid);
+    if let Err(error) =
+        fs::create_dir_all(GHOST_DIRECTORY).and_then(|_| fs::write(&path, run.to_string()))
+    {
+        error!("failed to save ghost to {path}: {error}");
+    }
+    personal_bests.0.insert(run.level_iid.clone(), run);
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersonalBests>()
+            .init_resource::<GhostRecorder>()
+            .init_resource::<GhostSettings>()
+            .add_systems(Startup, load_personal_bests)
+            .add_systems(Update, toggle_ghost)
+            .add_systems(Update, show_ghosts.after(toggle_ghost))
+            // `finish_level` runs on the fixed tick, before this
+            .add_systems(Update, save_personal_best)
+            .add_systems(Update, start_attempt.after(save_personal_best))
+            .add_systems(
+                FixedUpdate,
+                record_ghost_frames
+                    .run_if(character_select_closed)
+                    .run_if(not_waiting_for_replay)
+                    .run_if(not_playing_replay)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(FixedUpdate, rewind_ghosts.run_if(is_rewinding))
+            .add_systems(
+                FixedUpdate,
+                play_ghosts
+                    .run_if(character_select_closed)
+                    .run_if(not_waiting_for_replay)
+                    .in_set(SimulationSet),
+            );
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::SensorBundle;
+use crate::ground_detection::GroundSensor;
+use crate::water::apply_water_physics;
+use crate::SimulationSet;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum GravityDirection {
+    #[default]
+    Down,
+    Up,
+}
+
+impl GravityDirection {
+    /// 1 when "up" is up the screen, -1 when gravity is inverted.
+    pub fn up(&self) -> f32 {
+        match self {
+            GravityDirection::Down => 1.,
+            GravityDirection::Up => -1.,
+        }
+    }
+
+    pub fn inverted(&self) -> GravityDirection {
+        match self {
+            GravityDirection::Down => GravityDirection::Up,
+            GravityDirection::Up => GravityDirection::Down,
+        }
+    }
+}
+
+/// The gravity direction of the current level, read from its `gravity` Enum level field.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Resource)]
+pub struct LevelGravity(pub GravityDirection);
+
+/// Which way a body falls. Gravity zones invert the direction of their level.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Gravity {
+    pub direction: GravityDirection,
+    pub intersecting_zones: HashSet<Entity>,
+}
+
+impl Gravity {
+    pub fn up(&self) -> f32 {
+        self.direction.up()
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct GravityZone;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct GravityZoneBundle {
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    pub gravity_zone: GravityZone,
+}
+
+pub fn read_level_gravity(
+    mut level_events: EventReader<LevelEvent>,
+    mut level_gravity: ResMut<LevelGravity>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for level_event in level_events.read() {
+        let LevelEvent::Spawned(level_iid) = level_event else {
+            continue;
+        };
+
+        let ldtk_project = ldtk_project_assets
+            .get(ldtk_projects.single())
+            .expect("Project should be loaded if level has spawned");
+
+        let level = ldtk_project
+            .find_raw_level_by_level_selection(&LevelSelection::Iid(level_iid.clone()))
+            .expect("Spawned level should exist in LDtk project");
+
+        level_gravity.0 = match level.get_enum_field("gravity").map(String::as_str) {
+            Ok("Up") => GravityDirection::Up,
+            _ => GravityDirection::Down,
+        };
+    }
+}
+
+pub fn detect_gravity_zones(
+    mut bodies: Query<&mut Gravity>,
+    zones: Query<(), With<GravityZone>>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        match collision {
+            CollisionEvent::Started(collider_a, collider_b, _) => {
+                for (body, zone) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if !zones.contains(*zone) {
+                        continue;
+                    }
+                    if let Ok(mut gravity) = bodies.get_mut(*body) {
+                        gravity.intersecting_zones.insert(*zone);
+                    }
+                }
+            }
+            CollisionEvent::Stopped(collider_a, collider_b, _) => {
+                for (body, zone) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if let Ok(mut gravity) = bodies.get_mut(*body) {
+                        gravity.intersecting_zones.remove(zone);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn update_gravity_direction(level_gravity: Res<LevelGravity>, mut bodies: Query<&mut Gravity>) {
+    for mut gravity in &mut bodies {
+        let direction = if gravity.intersecting_zones.is_empty() {
+            level_gravity.0
+        } else {
+            level_gravity.0.inverted()
+        };
+        if gravity.direction != direction {
+            gravity.direction = direction;
+        }
+    }
+}
+
+/// Climbing and swimming decide how strong gravity is, this only decides which way it pulls.
+pub fn apply_gravity_direction(mut bodies: Query<(&Gravity, &mut GravityScale)>) {
+    for (gravity, mut gravity_scale) in &mut bodies {
+        let scale = gravity_scale.0.abs() * gravity.up();
+        if gravity_scale.0 != scale {
+            gravity_scale.0 = scale;
+        }
+    }
+}
+
+pub fn flip_sprites_with_gravity(mut bodies: Query<(&Gravity, &mut Sprite), Changed<Gravity>>) {
+    for (gravity, mut sprite) in &mut bodies {
+        sprite.flip_y = gravity.direction == GravityDirection::Up;
+    }
+}
+
+/// Keeps ground sensors on the side of their body that gravity pulls towards.
+pub fn orient_ground_sensors(
+    mut sensors: Query<(&GroundSensor, &mut Transform)>,
+    bodies: Query<&Gravity>,
+) {
+    for (sensor, mut transform) in &mut sensors {
+        if let Ok(gravity) = bodies.get(sensor.ground_detection_entity) {
+            let y = -transform.translation.y.abs() * gravity.up();
+            if transform.translation.y != y {
+                transform.translation.y = y;
+            }
+        }
+    }
+}
+
+pub struct GravityPlugin;
+
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelGravity>()
+            .register_ldtk_entity::<GravityZoneBundle>("GravityZone")
+            .add_systems(FixedUpdate, read_level_gravity.in_set(SimulationSet))
+            .add_systems(FixedUpdate, detect_gravity_zones.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                update_gravity_direction
+                    .after(read_level_gravity)
+                    .after(detect_gravity_zones)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                apply_gravity_direction
+                    .after(update_gravity_direction)
+                    .after(apply_water_physics)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, flip_sprites_with_gravity)
+            .add_systems(
+                FixedUpdate,
+                orient_ground_sensors
+                    .after(update_gravity_direction)
+                    .in_set(SimulationSet),
+            );
+    }
+}
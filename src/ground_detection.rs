@@ -4,15 +4,38 @@ use bevy::prelude::*;
 
 use bevy_rapier2d::prelude::*;
 
+use crate::constants::TILE_SIZE;
+use crate::conveyors::ConveyorBelt;
+use crate::enemies::Enemy;
+use crate::gravity::Gravity;
+use crate::one_way_platforms::{DropThrough, OneWayPlatform};
+use crate::slopes::Slope;
+use crate::walls::SurfaceMaterial;
+use crate::SimulationSet;
+
 #[derive(Component)]
 pub struct GroundSensor {
     pub ground_detection_entity: Entity,
     pub intersecting_ground_entities: HashSet<Entity>,
+    /// The intersecting ground that is stood on, without the one-way platforms being passed
+    /// through. Sorted, so that ties between several grounds go the same way every time.
+    pub standing_on: Vec<Entity>,
 }
 
 #[derive(Clone, Default, Component)]
 pub struct GroundDetection {
     pub on_ground: bool,
+    pub on_one_way_platform: bool,
+    /// The material of the ground under the sensor, if it is a wall.
+    /// When standing across several materials, the grippiest one wins.
+    pub ground_material: Option<SurfaceMaterial>,
+    /// The surface normal of the slope under the middle of the body, `None` on flat ground.
+    pub slope_normal: Option<Vec2>,
+    /// The collider under the sensor, preferring one that carries its riders.
+    pub ground_entity: Option<Entity>,
+    /// The velocity of the ground under the sensor, like a moving platform or a conveyor belt.
+    /// Zero on static ground.
+    pub ground_velocity: Vec2,
 }
 
 pub fn spawn_ground_sensor(
@@ -20,37 +43,37 @@ pub fn spawn_ground_sensor(
     detect_ground_for: Query<(Entity, &Collider), Added<GroundDetection>>,
 ) {
     for (entity, shape) in &detect_ground_for {
-        if let Some(cuboid) = shape.as_cuboid() {
-            let Vec2 {
-                x: half_extents_x,
-                y: half_extents_y,
-            } = cuboid.half_extents();
-
-            let detector_shape = Collider::cuboid(half_extents_x / 2.0, 2.);
-
-            let sensor_translation = Vec3::new(0., -half_extents_y, 0.);
-
-            commands.entity(entity).with_children(|builder| {
-                builder
-                    .spawn_empty()
-                    .insert(ActiveEvents::COLLISION_EVENTS)
-                    .insert(detector_shape)
-                    .insert(Sensor)
-                    .insert(Transform::from_translation(sensor_translation))
-                    .insert(GlobalTransform::default())
-                    .insert(GroundSensor {
-                        ground_detection_entity: entity,
-                        intersecting_ground_entities: HashSet::new(),
-                    });
-            });
-        }
+        // the bounds rather than a cuboid, as the player's collider has rounded corners
+        let half_extents = shape.raw.compute_local_aabb().half_extents();
+        let (half_extents_x, half_extents_y) = (half_extents.x, half_extents.y);
+
+        let detector_shape = Collider::cuboid(half_extents_x / 2.0, 2.);
+
+        let sensor_translation = Vec3::new(0., -half_extents_y, 0.);
+
+        commands.entity(entity).with_children(|builder| {
+            builder
+                .spawn_empty()
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                .insert(detector_shape)
+                .insert(Sensor)
+                .insert(Transform::from_translation(sensor_translation))
+                .insert(GlobalTransform::default())
+                .insert(GroundSensor {
+                    ground_detection_entity: entity,
+                    intersecting_ground_entities: HashSet::new(),
+                    standing_on: Vec::new(),
+                });
+        });
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn ground_detection(
     mut ground_sensors: Query<&mut GroundSensor>,
     mut collisions: EventReader<CollisionEvent>,
-    collidables: Query<Entity, (With<Collider>, Without<Sensor>)>,
+    // enemies are solid, but standing on one means stomping it rather than being on the ground
+    collidables: Query<Entity, (With<Collider>, Without<Sensor>, Without<Enemy>)>,
 ) {
     for collision_event in collisions.read() {
         match collision_event {
@@ -80,13 +103,115 @@ pub fn ground_detection(
     }
 }
 
+/// A body passing up through a one-way platform or the top of a ladder overlaps it without
+/// standing on it, so those only count as ground once it stops moving away from them.
+/// Runs every frame, since that changes without the sensor noticing.
 pub fn update_on_ground(
+    mut ground_detectors: Query<(
+        &mut GroundDetection,
+        &Velocity,
+        Option<&Gravity>,
+        Has<DropThrough>,
+    )>,
+    mut ground_sensors: Query<&mut GroundSensor>,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+    surface_materials: Query<&SurfaceMaterial>,
+) {
+    for mut sensor in &mut ground_sensors {
+        let Ok((mut ground_detection, velocity, gravity, dropping)) =
+            ground_detectors.get_mut(sensor.ground_detection_entity)
+        else {
+            continue;
+        };
+
+        let up = gravity.map_or(1., Gravity::up);
+        let passing_through = dropping || velocity.linvel.y * up > 1.;
+        let mut ground: Vec<Entity> = sensor
+            .intersecting_ground_entities
+            .iter()
+            .filter(|entity| !passing_through || !one_way_platforms.contains(**entity))
+            .copied()
+            .collect();
+        ground.sort();
+
+        ground_detection.on_ground = !ground.is_empty();
+        ground_detection.on_one_way_platform = ground
+            .iter()
+            .any(|entity| one_way_platforms.contains(*entity));
+        ground_detection.ground_material = ground
+            .iter()
+            .filter_map(|entity| surface_materials.get(*entity).ok())
+            .max_by(|a, b| a.friction().total_cmp(&b.friction()))
+            .copied();
+        sensor.standing_on = ground;
+    }
+}
+
+/// Runs every frame, since the ground can change speed without the sensor noticing.
+/// Uses the ground found by `update_on_ground`, so a body jumping up through a moving one-way
+/// platform isn't dragged along by it.
+pub fn update_ground_velocity(
     mut ground_detectors: Query<&mut GroundDetection>,
-    ground_sensors: Query<&GroundSensor, Changed<GroundSensor>>,
+    ground_sensors: Query<&GroundSensor>,
+    bodies: Query<(&RigidBody, &Velocity)>,
+    conveyors: Query<&ConveyorBelt>,
 ) {
     for sensor in &ground_sensors {
         if let Ok(mut ground_detection) = ground_detectors.get_mut(sensor.ground_detection_entity) {
-            ground_detection.on_ground = !sensor.intersecting_ground_entities.is_empty();
+            let surface_velocity = |entity: &Entity| match bodies.get(*entity) {
+                Ok((RigidBody::KinematicVelocityBased, velocity)) => Some(velocity.linvel),
+                _ => conveyors
+                    .get(*entity)
+                    .ok()
+                    .map(ConveyorBelt::surface_velocity),
+            };
+
+            // the ground is sorted, so ties between several grounds go to the lowest entity,
+            // which is the same every time the level is played
+            let moving_ground = sensor
+                .standing_on
+                .iter()
+                .find_map(|entity| surface_velocity(entity).map(|velocity| (*entity, velocity)));
+
+            if let Some((entity, velocity)) = moving_ground {
+                ground_detection.ground_entity = Some(entity);
+                ground_detection.ground_velocity = velocity;
+            } else {
+                ground_detection.ground_entity = sensor.standing_on.first().copied();
+                ground_detection.ground_velocity = Vec2::ZERO;
+            }
+        }
+    }
+}
+
+/// Only a slope under the middle of the body counts. The sensor also brushes the slopes
+/// beside flat ground, and the body moves along them without the sensor noticing.
+pub fn update_slope_normal(
+    mut ground_detectors: Query<(&Transform, &mut GroundDetection)>,
+    ground_sensors: Query<&GroundSensor>,
+    slopes: Query<(&Slope, &GlobalTransform)>,
+) {
+    for sensor in &ground_sensors {
+        if let Ok((transform, mut ground_detection)) =
+            ground_detectors.get_mut(sensor.ground_detection_entity)
+        {
+            let distance_to = |slope_transform: &GlobalTransform| {
+                (slope_transform.translation().x - transform.translation.x).abs()
+            };
+
+            let slope_normal = sensor
+                .intersecting_ground_entities
+                .iter()
+                .filter_map(|entity| slopes.get(*entity).ok())
+                .filter(|(_, slope_transform)| {
+                    distance_to(slope_transform) <= TILE_SIZE as f32 / 2.
+                })
+                .min_by(|(_, a), (_, b)| distance_to(a).total_cmp(&distance_to(b)))
+                .map(|(slope, _)| slope.normal());
+
+            if ground_detection.slope_normal != slope_normal {
+                ground_detection.slope_normal = slope_normal;
+            }
         }
     }
 }
@@ -97,7 +222,24 @@ pub struct GroundDetectionPlugin;
 impl Plugin for GroundDetectionPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, spawn_ground_sensor)
-            .add_systems(Update, ground_detection)
-            .add_systems(Update, update_on_ground);
+            .add_systems(FixedUpdate, ground_detection.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                update_on_ground
+                    .after(ground_detection)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                update_ground_velocity
+                    .after(update_on_ground)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                update_slope_normal
+                    .after(ground_detection)
+                    .in_set(SimulationSet),
+            );
     }
 }
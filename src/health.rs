@@ -0,0 +1,218 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game_flow::RestartLevel;
+use crate::gravity::Gravity;
+use crate::player::player_movement;
+use crate::SimulationSet;
+
+pub const KNOCKBACK_SECONDS: f32 = 0.3;
+pub const KNOCKBACK_VELOCITY: Vec2 = Vec2::new(200., 250.);
+pub const INVINCIBILITY_SECONDS: f32 = 1.5;
+pub const BLINKS_PER_SECOND: f32 = 10.;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Resource)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn max_health(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => 3,
+            Difficulty::Hard => 1,
+        }
+    }
+}
+
+/// Filled in from the `Difficulty` when spawned and whenever the level restarts.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+/// Sent by hazards and enemies to hurt an entity with `Health`.
+#[derive(Copy, Clone, PartialEq, Debug, Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    /// Where the damage came from, to knock the target away from
+    pub source: Vec2,
+}
+
+/// Takes away control from `player_movement` while the target is thrown back.
+#[derive(Clone, Debug, Component)]
+pub struct Knockback(Timer);
+
+/// Damage is ignored until this runs out, and the sprite blinks meanwhile.
+#[derive(Clone, Debug, Component)]
+pub struct Invincible(Timer);
+
+fn init_health(difficulty: Res<Difficulty>, mut healths: Query<&mut Health, Added<Health>>) {
+    for mut health in &mut healths {
+        health.max = difficulty.max_health();
+        health.current = health.max;
+    }
+}
+
+pub fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<
+        (
+            &mut Health,
+            &GlobalTransform,
+            &mut Velocity,
+            Option<&Gravity>,
+        ),
+        Without<Invincible>,
+    >,
+    mut restart_level_writer: EventWriter<RestartLevel>,
+) {
+    // Invincible is only inserted at the end of the frame, so several hits in one frame only
+    // count once by remembering who was already hurt
+    let mut hurt = Vec::new();
+
+    for damage in damage_events.read() {
+        if hurt.contains(&damage.target) {
+            continue;
+        }
+        let Ok((mut health, transform, mut velocity, gravity)) = targets.get_mut(damage.target)
+        else {
+            continue;
+        };
+        hurt.push(damage.target);
+        if health.current == 0 {
+            continue;
+        }
+
+        health.current = health.current.saturating_sub(damage.amount);
+        if health.current == 0 {
+            restart_level_writer.send(RestartLevel);
+            continue;
+        }
+
+        let away = if transform.translation().x < damage.source.x {
+            -1.
+        } else {
+            1.
+        };
+        let up = gravity.map_or(1., Gravity::up);
+        velocity.linvel = Vec2::new(KNOCKBACK_VELOCITY.x * away, KNOCKBACK_VELOCITY.y * up);
+
+        commands.entity(damage.target).insert((
+            Knockback(Timer::from_seconds(KNOCKBACK_SECONDS, TimerMode::Once)),
+            Invincible(Timer::from_seconds(INVINCIBILITY_SECONDS, TimerMode::Once)),
+        ));
+    }
+}
+
+pub fn tick_knockback(
+    mut commands: Commands,
+    mut knocked_back: Query<(Entity, &mut Knockback)>,
+    time: Res<Time>,
+) {
+    for (entity, mut knockback) in &mut knocked_back {
+        if knockback.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+fn tick_invincibility(
+    mut commands: Commands,
+    mut invincibles: Query<(Entity, &mut Invincible)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invincible) in &mut invincibles {
+        if invincible.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invincible>();
+        }
+    }
+}
+
+pub fn blink_while_invincible(
+    mut invincibles: Query<(&Invincible, &mut Visibility)>,
+    mut visibilities: Query<&mut Visibility, Without<Invincible>>,
+    mut removed_invincibles: RemovedComponents<Invincible>,
+) {
+    for (invincible, mut visibility) in &mut invincibles {
+        let blink = (invincible.0.elapsed_secs() * BLINKS_PER_SECOND) as u32 % 2 == 0;
+        *visibility = if blink {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+
+    for entity in removed_invincibles.read() {
+        if let Ok(mut visibility) = visibilities.get_mut(entity) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn cycle_difficulty(keyboard_input: Res<ButtonInput<KeyCode>>, mut difficulty: ResMut<Difficulty>) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        *difficulty = difficulty.next();
+    }
+}
+
+/// Health is refilled on restarts and whenever the difficulty is changed with F4.
+fn reset_health(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    difficulty: Res<Difficulty>,
+    mut healths: Query<(Entity, &mut Health, &mut Visibility)>,
+) {
+    if restart_level_reader.read().count() == 0 && !difficulty.is_changed() {
+        return;
+    }
+
+    for (entity, mut health, mut visibility) in &mut healths {
+        health.max = difficulty.max_health();
+        health.current = health.max;
+        *visibility = Visibility::Inherited;
+        commands
+            .entity(entity)
+            .remove::<Knockback>()
+            .remove::<Invincible>();
+    }
+}
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .add_event::<DamageEvent>()
+            .add_systems(Update, init_health)
+            // `Knockback` is applied before the player's movement runs in the same tick,
+            // so their steering can't cancel the knockback
+            .add_systems(
+                FixedUpdate,
+                (apply_damage, apply_deferred)
+                    .chain()
+                    .before(player_movement)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(FixedUpdate, tick_knockback.in_set(SimulationSet))
+            .add_systems(FixedUpdate, tick_invincibility.in_set(SimulationSet))
+            .add_systems(Update, blink_while_invincible)
+            .add_systems(Update, cycle_difficulty)
+            .add_systems(Update, reset_health.after(cycle_difficulty));
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::health::Health;
+use crate::keys::Inventory;
+use crate::player::Player;
+
+/// The row of coloured squares showing the keys the first player carries.
+#[derive(Component)]
+struct KeyHud;
+
+/// The row of hearts showing the first player's health, under the keys.
+#[derive(Component)]
+struct HealthHud;
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(28.),
+                left: Val::Px(8.),
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(HealthHud);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                left: Val::Px(8.),
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(KeyHud);
+}
+
+fn update_key_hud(
+    mut commands: Commands,
+    inventories: Query<(&Player, &Inventory), Changed<Inventory>>,
+    hud: Query<Entity, With<KeyHud>>,
+) {
+    let inventory = inventories
+        .iter()
+        .find_map(|(player, inventory)| (player.index == 0).then_some(inventory));
+    let (Some(inventory), Ok(hud)) = (inventory, hud.get_single()) else {
+        return;
+    };
+
+    commands
+        .entity(hud)
+        .despawn_descendants()
+        .with_children(|hud| {
+            for key in &inventory.keys {
+                hud.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(16.),
+                        height: Val::Px(16.),
+                        ..default()
+                    },
+                    background_color: key.color().into(),
+                    ..default()
+                });
+            }
+        });
+}
+
+fn update_health_hud(
+    mut commands: Commands,
+    healths: Query<(&Player, &Health), Changed<Health>>,
+    hud: Query<Entity, With<HealthHud>>,
+) {
+    let health = healths
+        .iter()
+        .find_map(|(player, health)| (player.index == 0).then_some(health));
+    let (Some(health), Ok(hud)) = (health, hud.get_single()) else {
+        return;
+    };
+
+    commands
+        .entity(hud)
+        .despawn_descendants()
+        .with_children(|hud| {
+            for heart in 0..health.max {
+                let color = if heart < health.current {
+                    Color::srgb(0.85, 0.15, 0.25)
+                } else {
+                    Color::srgba(0.3, 0.3, 0.3, 0.6)
+                };
+                hud.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(12.),
+                        height: Val::Px(12.),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                });
+            }
+        });
+}
+
+/// Heads-up display drawn over the level.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, update_key_hud)
+            .add_systems(Update, update_health_hud);
+    }
+}
@@ -12,39 +12,127 @@ pub enum PlayerAction {
     Left,
     Right,
     Jump,
+    Rewind,
+}
+
+pub const MAX_PLAYERS: usize = 2;
+
+/// How many local players are playing, toggled between 1 and `MAX_PLAYERS` with F2.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Resource)]
+pub struct PlayerCount(pub usize);
+
+impl Default for PlayerCount {
+    fn default() -> Self {
+        PlayerCount(1)
+    }
 }
 
 pub struct InputPlugin;
 
-fn setup_controls(mut commands: Commands) {
-    let mut input_map = InputMap::default();
+fn insert_wasd(input_map: &mut InputMap<PlayerAction>) {
     input_map.insert_multiple([
         (PlayerAction::Left, KeyCode::KeyA),
-        (PlayerAction::Left, KeyCode::ArrowLeft),
         (PlayerAction::Right, KeyCode::KeyD),
-        (PlayerAction::Right, KeyCode::ArrowRight),
         (PlayerAction::Up, KeyCode::KeyW),
-        (PlayerAction::Up, KeyCode::ArrowUp),
         (PlayerAction::Down, KeyCode::KeyS),
-        (PlayerAction::Down, KeyCode::ArrowDown),
         (PlayerAction::Jump, KeyCode::Space),
+        (PlayerAction::Rewind, KeyCode::KeyQ),
     ]);
+}
+
+fn insert_arrows(input_map: &mut InputMap<PlayerAction>) {
     input_map.insert_multiple([
-        (PlayerAction::Left, GamepadButtonType::DPadLeft),
-        (PlayerAction::Right, GamepadButtonType::DPadRight),
-        (PlayerAction::Up, GamepadButtonType::DPadUp),
-        (PlayerAction::Down, GamepadButtonType::DPadDown),
-        (PlayerAction::Jump, GamepadButtonType::South),
+        (PlayerAction::Left, KeyCode::ArrowLeft),
+        (PlayerAction::Right, KeyCode::ArrowRight),
+        (PlayerAction::Up, KeyCode::ArrowUp),
+        (PlayerAction::Down, KeyCode::ArrowDown),
+        (PlayerAction::Jump, KeyCode::Enter),
+        (PlayerAction::Rewind, KeyCode::ShiftRight),
     ]);
-    input_map.insert_dual_axis(PlayerAction::Move, GamepadStick::LEFT);
-    commands
-        .spawn(InputManagerBundle::with_map(input_map))
-        .insert(Player::default());
+}
+
+/// A lone player gets every binding. With more players, the keyboard is split between the
+/// first two and each player only listens to the gamepad assigned to them, if any.
+pub fn input_map(
+    player_index: usize,
+    player_count: usize,
+    gamepad: Option<Gamepad>,
+) -> InputMap<PlayerAction> {
+    let mut input_map = InputMap::default();
+
+    match (player_count, player_index) {
+        (1, _) => {
+            insert_wasd(&mut input_map);
+            insert_arrows(&mut input_map);
+        }
+        (_, 0) => insert_wasd(&mut input_map),
+        (_, 1) => insert_arrows(&mut input_map),
+        _ => (),
+    }
+
+    if player_count == 1 || gamepad.is_some() {
+        input_map.insert_multiple([
+            (PlayerAction::Left, GamepadButtonType::DPadLeft),
+            (PlayerAction::Right, GamepadButtonType::DPadRight),
+            (PlayerAction::Up, GamepadButtonType::DPadUp),
+            (PlayerAction::Down, GamepadButtonType::DPadDown),
+            (PlayerAction::Jump, GamepadButtonType::South),
+            (PlayerAction::Rewind, GamepadButtonType::LeftTrigger),
+        ]);
+        input_map.insert_dual_axis(PlayerAction::Move, GamepadStick::LEFT);
+    }
+    if let Some(gamepad) = gamepad {
+        input_map.set_gamepad(gamepad);
+    }
+
+    input_map
+}
+
+fn toggle_player_count(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut player_count: ResMut<PlayerCount>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        player_count.0 = if player_count.0 >= MAX_PLAYERS {
+            1
+        } else {
+            player_count.0 + 1
+        };
+    }
+}
+
+/// Gamepads are handed out to players by id, which follows the order they were connected in.
+/// `Gamepads` iterates in no particular order, so they are sorted first.
+fn update_input_maps(
+    player_count: Res<PlayerCount>,
+    gamepads: Res<Gamepads>,
+    mut players: Query<(&Player, &mut InputMap<PlayerAction>)>,
+    added_players: Query<(), Added<InputMap<PlayerAction>>>,
+) {
+    if !player_count.is_changed() && !gamepads.is_changed() && added_players.is_empty() {
+        return;
+    }
+
+    let mut sorted_gamepads: Vec<Gamepad> = gamepads.iter().collect();
+    sorted_gamepads.sort_by_key(|gamepad| gamepad.id);
+
+    for (player, mut map) in &mut players {
+        let gamepad = if player_count.0 == 1 {
+            None
+        } else {
+            sorted_gamepads.get(player.index).copied()
+        };
+        *map = input_map(player.index, player_count.0, gamepad);
+    }
 }
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
+        // `ActionState` keeps a separate state for `FixedUpdate`, so a press made between
+        // two fixed ticks is `just_pressed` during exactly one of them
         app.add_plugins(InputManagerPlugin::<PlayerAction>::default())
-            .add_systems(Startup, setup_controls);
+            .init_resource::<PlayerCount>()
+            .add_systems(Update, toggle_player_count)
+            .add_systems(Update, update_input_maps.after(toggle_player_count));
     }
 }
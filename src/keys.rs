@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::{ColliderBundle, SensorBundle};
+use crate::game_flow::RestartLevel;
+use crate::player::Player;
+use crate::rewind::Rewindable;
+use crate::SimulationSet;
+
+/// Read from the `colour` Enum field of `Key` and `LockedDoor` entities.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum KeyColour {
+    #[default]
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl From<&EntityInstance> for KeyColour {
+    fn from(entity_instance: &EntityInstance) -> KeyColour {
+        match entity_instance.get_enum_field("colour").map(String::as_str) {
+            Ok("Green") => KeyColour::Green,
+            Ok("Blue") => KeyColour::Blue,
+            Ok("Yellow") => KeyColour::Yellow,
+            _ => KeyColour::Red,
+        }
+    }
+}
+
+impl KeyColour {
+    pub fn color(&self) -> Color {
+        match self {
+            KeyColour::Red => Color::srgb(0.85, 0.2, 0.2),
+            KeyColour::Green => Color::srgb(0.2, 0.75, 0.3),
+            KeyColour::Blue => Color::srgb(0.25, 0.4, 0.9),
+            KeyColour::Yellow => Color::srgb(0.95, 0.8, 0.2),
+        }
+    }
+}
+
+/// The keys a player is carrying, in the order they were picked up.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Inventory {
+    pub keys: Vec<KeyColour>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Key {
+    pub colour: KeyColour,
+    pub collected: bool,
+}
+
+impl From<&EntityInstance> for Key {
+    fn from(entity_instance: &EntityInstance) -> Key {
+        Key {
+            colour: KeyColour::from(entity_instance),
+            collected: false,
+        }
+    }
+}
+
+/// A solid door that opens when a player carrying a key of its colour touches it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct LockedDoor {
+    pub colour: KeyColour,
+    pub open: bool,
+}
+
+impl From<&EntityInstance> for LockedDoor {
+    fn from(entity_instance: &EntityInstance) -> LockedDoor {
+        LockedDoor {
+            colour: KeyColour::from(entity_instance),
+            open: false,
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct KeyBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub key: Key,
+    pub rewindable: Rewindable,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct LockedDoorBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub locked_door: LockedDoor,
+    pub rewindable: Rewindable,
+}
+
+/// Keys and doors are hidden and have their colliders disabled rather than being despawned,
+/// so that restarting the level can bring them back.
+fn set_present(
+    commands: &mut Commands,
+    entity: Entity,
+    visibility: &mut Visibility,
+    present: bool,
+) {
+    if present {
+        commands.entity(entity).remove::<ColliderDisabled>();
+        *visibility = Visibility::Inherited;
+    } else {
+        commands.entity(entity).insert(ColliderDisabled);
+        *visibility = Visibility::Hidden;
+    }
+}
+
+pub fn collect_keys(
+    mut commands: Commands,
+    mut players: Query<&mut Inventory, With<Player>>,
+    mut keys: Query<(&mut Key, &mut Visibility)>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        if let CollisionEvent::Started(collider_a, collider_b, _) = collision {
+            for (key_entity, player) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                if let (Ok((mut key, mut visibility)), Ok(mut inventory)) =
+                    (keys.get_mut(*key_entity), players.get_mut(*player))
+                {
+                    if !key.collected {
+                        key.collected = true;
+                        inventory.keys.push(key.colour);
+                        set_present(&mut commands, *key_entity, &mut visibility, false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn unlock_doors(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    mut players: Query<(Entity, &mut Inventory), With<Player>>,
+    mut doors: Query<(&mut LockedDoor, &mut Visibility)>,
+) {
+    for (player, mut inventory) in &mut players {
+        for contact_pair in rapier_context.contact_pairs_with(player) {
+            if !contact_pair.has_any_active_contacts() {
+                continue;
+            }
+
+            let other = if contact_pair.collider1() == player {
+                contact_pair.collider2()
+            } else {
+                contact_pair.collider1()
+            };
+
+            let Ok((mut door, mut visibility)) = doors.get_mut(other) else {
+                continue;
+            };
+            if door.open {
+                continue;
+            }
+
+            if let Some(index) = inventory.keys.iter().position(|key| *key == door.colour) {
+                inventory.keys.remove(index);
+                door.open = true;
+                set_present(&mut commands, other, &mut visibility, false);
+            }
+        }
+    }
+}
+
+fn reset_keys_and_doors(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut inventories: Query<&mut Inventory>,
+    mut keys: Query<(Entity, &mut Key, &mut Visibility), Without<LockedDoor>>,
+    mut doors: Query<(Entity, &mut LockedDoor, &mut Visibility), Without<Key>>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for mut inventory in &mut inventories {
+        inventory.keys.clear();
+    }
+    for (entity, mut key, mut visibility) in &mut keys {
+        key.collected = false;
+        set_present(&mut commands, entity, &mut visibility, true);
+    }
+    for (entity, mut door, mut visibility) in &mut doors {
+        door.open = false;
+        set_present(&mut commands, entity, &mut visibility, true);
+    }
+}
+
+pub struct KeyPlugin;
+
+impl Plugin for KeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<KeyBundle>("Key")
+            .register_ldtk_entity::<LockedDoorBundle>("LockedDoor")
+            .add_systems(FixedUpdate, collect_keys.in_set(SimulationSet))
+            .add_systems(FixedUpdate, unlock_doors.in_set(SimulationSet))
+            .add_systems(Update, reset_keys_and_doors);
+    }
+}
@@ -0,0 +1,401 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::colliders::{ColliderBundle, SensorBundle};
+use crate::crates::Crate;
+use crate::game_flow::RestartLevel;
+use crate::gravity::Gravity;
+use crate::input::PlayerAction;
+use crate::player::Player;
+use crate::rewind::Rewindable;
+use crate::wind::WindsweptBundle;
+use crate::SimulationSet;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum SourceKind {
+    /// Turns on when the player touches it, and back off after its duration.
+    /// A duration of 0 keeps it on until the level restarts.
+    Button,
+    /// On for as long as anything with weight rests on it.
+    #[default]
+    PressurePlate,
+    /// Flipped by pressing Up while touching it.
+    Lever,
+}
+
+/// Emits an on/off signal to the entities referenced by its `targets` EntityRef array field.
+#[derive(Clone, Debug, Default, Component)]
+pub struct SignalSource {
+    pub kind: SourceKind,
+    pub on: bool,
+    targets: Vec<String>,
+    touching: HashSet<Entity>,
+    timer: Timer,
+}
+
+impl From<&EntityInstance> for SignalSource {
+    fn from(entity_instance: &EntityInstance) -> SignalSource {
+        let kind = match entity_instance.identifier.as_ref() {
+            "Button" => SourceKind::Button,
+            "Lever" => SourceKind::Lever,
+            _ => SourceKind::PressurePlate,
+        };
+
+        let targets = entity_instance
+            .get_entity_refs_field("targets")
+            .map(|targets| {
+                targets
+                    .into_iter()
+                    .map(|target| target.entity_iid.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let duration = entity_instance
+            .get_float_field("duration")
+            .copied()
+            .unwrap_or(1.0);
+
+        SignalSource {
+            kind,
+            on: false,
+            targets,
+            touching: HashSet::new(),
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+}
+
+impl SignalSource {
+    fn press(&mut self) {
+        self.on = true;
+        self.timer.reset();
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Logic {
+    /// On while every source targeting it is on.
+    And,
+    /// On while any source targeting it is on.
+    #[default]
+    Or,
+    /// Flips every time any source turns on.
+    Toggle,
+    /// Turns on when any source turns on, and back off after its duration.
+    Timer,
+}
+
+/// Combines the signals of every source targeting this entity, according to its `logic` field.
+#[derive(Clone, Debug, Default, Component)]
+pub struct SignalReceiver {
+    pub logic: Logic,
+    pub on: bool,
+    /// Whether any source targets this receiver at all
+    pub wired: bool,
+    previous_input: bool,
+    timer: Timer,
+}
+
+impl From<&EntityInstance> for SignalReceiver {
+    fn from(entity_instance: &EntityInstance) -> SignalReceiver {
+        let logic = match entity_instance.get_enum_field("logic").map(String::as_str) {
+            Ok("And") => Logic::And,
+            Ok("Toggle") => Logic::Toggle,
+            Ok("Timer") => Logic::Timer,
+            _ => Logic::Or,
+        };
+
+        let duration = entity_instance
+            .get_float_field("duration")
+            .copied()
+            .unwrap_or(1.0);
+
+        SignalReceiver {
+            logic,
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+            ..Default::default()
+        }
+    }
+}
+
+/// Solid while its signal is off, open while it is on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Door;
+
+/// Spawns a crate every time its signal turns on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Spawner {
+    was_on: bool,
+}
+
+/// Crates made by a `Spawner`, which are removed again when the level restarts.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct SpawnedCrate;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SignalSourceBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub signal_source: SignalSource,
+    pub rewindable: Rewindable,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub signal_receiver: SignalReceiver,
+    pub door: Door,
+    pub rewindable: Rewindable,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SpawnerBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub signal_receiver: SignalReceiver,
+    pub spawner: Spawner,
+    pub rewindable: Rewindable,
+}
+
+/// Pressure plates only notice bodies with weight, which also rules out other sensors.
+pub fn detect_signal_contacts(
+    mut sources: Query<&mut SignalSource>,
+    bodies: Query<&RigidBody>,
+    players: Query<(), With<Player>>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        match collision {
+            CollisionEvent::Started(collider_a, collider_b, _) => {
+                for (source_entity, other) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if let Ok(mut source) = sources.get_mut(*source_entity) {
+                        if matches!(bodies.get(*other), Ok(RigidBody::Dynamic)) {
+                            source.touching.insert(*other);
+                        }
+                        if source.kind == SourceKind::Button && players.contains(*other) {
+                            source.press();
+                        }
+                    }
+                }
+            }
+            CollisionEvent::Stopped(collider_a, collider_b, _) => {
+                for (source_entity, other) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if let Ok(mut source) = sources.get_mut(*source_entity) {
+                        source.touching.remove(other);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn update_signal_sources(
+    mut sources: Query<&mut SignalSource>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    time: Res<Time>,
+) {
+    for mut source in &mut sources {
+        match source.kind {
+            SourceKind::Button => {
+                let duration = source.timer.duration();
+                if source.on && !duration.is_zero() && source.timer.tick(time.delta()).finished() {
+                    source.on = false;
+                }
+            }
+            SourceKind::PressurePlate => {
+                source.on = !source.touching.is_empty();
+            }
+            SourceKind::Lever => {
+                // flipped by any touching player pressing Up
+                let interact = players
+                    .iter_many(&source.touching)
+                    .any(|input| input.just_pressed(&PlayerAction::Up));
+                if interact {
+                    source.on = !source.on;
+                }
+            }
+        }
+    }
+}
+
+pub fn propagate_signals(
+    sources: Query<&SignalSource>,
+    mut receivers: Query<(&EntityIid, &mut SignalReceiver)>,
+    time: Res<Time>,
+) {
+    let mut inputs: HashMap<&str, Vec<bool>> = HashMap::new();
+    for source in &sources {
+        for target in &source.targets {
+            inputs.entry(target.as_str()).or_default().push(source.on);
+        }
+    }
+
+    for (entity_iid, mut receiver) in &mut receivers {
+        let Some(signals) = inputs.get(entity_iid.as_str()) else {
+            receiver.wired = false;
+            continue;
+        };
+        receiver.wired = true;
+
+        let input = match receiver.logic {
+            Logic::And => signals.iter().all(|on| *on),
+            Logic::Or | Logic::Toggle | Logic::Timer => signals.iter().any(|on| *on),
+        };
+        let rising_edge = input && !receiver.previous_input;
+        receiver.previous_input = input;
+
+        match receiver.logic {
+            Logic::And | Logic::Or => receiver.on = input,
+            Logic::Toggle => {
+                if rising_edge {
+                    receiver.on = !receiver.on;
+                }
+            }
+            Logic::Timer => {
+                if rising_edge {
+                    receiver.on = true;
+                    receiver.timer.reset();
+                } else if receiver.on && receiver.timer.tick(time.delta()).finished() {
+                    receiver.on = false;
+                }
+            }
+        }
+    }
+}
+
+pub fn open_doors(
+    mut commands: Commands,
+    mut doors: Query<
+        (
+            Entity,
+            &SignalReceiver,
+            &mut Visibility,
+            Has<ColliderDisabled>,
+        ),
+        With<Door>,
+    >,
+) {
+    for (entity, receiver, mut visibility, open) in &mut doors {
+        if receiver.on && !open {
+            commands.entity(entity).insert(ColliderDisabled);
+            *visibility = Visibility::Hidden;
+        } else if !receiver.on && open {
+            commands.entity(entity).remove::<ColliderDisabled>();
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn spawn_crates(
+    mut commands: Commands,
+    mut spawners: Query<(
+        &mut Spawner,
+        &SignalReceiver,
+        &EntityInstance,
+        &Transform,
+        &Handle<Image>,
+        &TextureAtlas,
+        &Parent,
+    )>,
+) {
+    for (mut spawner, receiver, entity_instance, transform, texture, atlas, parent) in &mut spawners
+    {
+        if receiver.on && !spawner.was_on {
+            // spawned crates share the size and look of their spawner
+            let mut crate_instance = entity_instance.clone();
+            crate_instance.identifier = "Crate".to_string();
+
+            commands.entity(parent.get()).with_children(|layer| {
+                layer
+                    .spawn(SpriteBundle {
+                        texture: texture.clone(),
+                        transform: *transform,
+                        ..Default::default()
+                    })
+                    .insert(atlas.clone())
+                    .insert(ColliderBundle::from(&crate_instance))
+                    .insert(Crate::default())
+                    .insert(Gravity::default())
+                    .insert(WindsweptBundle::default())
+                    .insert(Rewindable)
+                    .insert(SpawnedCrate);
+            });
+        }
+        spawner.was_on = receiver.on;
+    }
+}
+
+fn reset_logic(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut sources: Query<&mut SignalSource>,
+    mut receivers: Query<&mut SignalReceiver>,
+    spawned_crates: Query<Entity, With<SpawnedCrate>>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for mut source in &mut sources {
+        source.on = false;
+        source.timer.reset();
+    }
+    for mut receiver in &mut receivers {
+        receiver.on = false;
+        receiver.previous_input = false;
+        receiver.timer.reset();
+    }
+    for entity in &spawned_crates {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Wires buttons, pressure plates and levers to doors, moving platforms and crate spawners.
+pub struct LogicPlugin;
+
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<SignalSourceBundle>("Button")
+            .register_ldtk_entity::<SignalSourceBundle>("PressurePlate")
+            .register_ldtk_entity::<SignalSourceBundle>("Lever")
+            .register_ldtk_entity::<DoorBundle>("Door")
+            .register_ldtk_entity::<SpawnerBundle>("Spawner")
+            .add_systems(FixedUpdate, detect_signal_contacts.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                update_signal_sources
+                    .after(detect_signal_contacts)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                propagate_signals
+                    .after(update_signal_sources)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                open_doors.after(propagate_signals).in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                spawn_crates.after(propagate_signals).in_set(SimulationSet),
+            )
+            .add_systems(Update, reset_logic);
+    }
+}
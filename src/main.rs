@@ -3,27 +3,84 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 mod audio;
+mod characters;
 mod climbing;
 mod colliders;
 mod constants;
+mod conveyors;
+mod crates;
+mod crumble;
+mod enemies;
 mod game_flow;
+mod ghost;
+mod gravity;
 mod ground_detection;
+mod health;
+mod hud;
 mod input;
+mod keys;
+mod logic;
+mod moving_platforms;
+mod one_way_platforms;
 mod player;
+mod replay;
+mod rewind;
+mod slopes;
+mod springs;
+mod switches;
+mod teleporters;
+mod turrets;
 mod utils;
 mod walls;
+mod water;
+mod wind;
+
+/// The gameplay systems in `FixedUpdate` that move the simulation forward by one tick.
+#[derive(SystemSet, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SimulationSet;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(LdtkPlugin)
         .add_plugins(audio::AudioPlugin)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugins(
+            RapierPhysicsPlugin::<one_way_platforms::OneWayPlatformHooks>::pixels_per_meter(100.0)
+                .in_schedule(FixedPostUpdate),
+        )
+        // one physics step per fixed tick, after all of that tick's gameplay in `FixedUpdate`,
+        // so the game plays out the same at any frame rate
+        .insert_resource(TimestepMode::Fixed {
+            dt: Time::<Fixed>::default().timestep().as_secs_f32(),
+            substeps: 1,
+        })
         .add_plugins(game_flow::GameFlowPlugin)
         .add_plugins(input::InputPlugin)
         .add_plugins(player::PlayerPlugin)
         .add_plugins(walls::WallPlugin)
         .add_plugins(ground_detection::GroundDetectionPlugin)
         .add_plugins(climbing::ClimbingPlugin)
+        .add_plugins(one_way_platforms::OneWayPlatformPlugin)
+        .add_plugins(water::WaterPlugin)
+        .add_plugins(slopes::SlopePlugin)
+        .add_plugins(moving_platforms::MovingPlatformPlugin)
+        .add_plugins(crates::CratePlugin)
+        .add_plugins(logic::LogicPlugin)
+        .add_plugins(keys::KeyPlugin)
+        .add_plugins(hud::HudPlugin)
+        .add_plugins(switches::SwitchPlugin)
+        .add_plugins(springs::SpringPlugin)
+        .add_plugins(conveyors::ConveyorPlugin)
+        .add_plugins(crumble::CrumblePlugin)
+        .add_plugins(teleporters::TeleporterPlugin)
+        .add_plugins(gravity::GravityPlugin)
+        .add_plugins(wind::WindPlugin)
+        .add_plugins(enemies::EnemyPlugin)
+        .add_plugins(turrets::TurretPlugin)
+        .add_plugins(health::HealthPlugin)
+        .add_plugins(characters::CharacterPlugin)
+        .add_plugins(rewind::RewindPlugin)
+        .add_plugins(replay::ReplayPlugin)
+        .add_plugins(ghost::GhostPlugin)
         .run();
 }
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::ColliderBundle;
+use crate::constants::TILE_SIZE;
+use crate::logic::SignalReceiver;
+use crate::rewind::Rewindable;
+use crate::SimulationSet;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum PathMode {
+    /// Goes back to the first node after the last one.
+    Loop,
+    /// Goes back along the path in reverse after the last node.
+    #[default]
+    PingPong,
+}
+
+/// A kinematic platform travelling along a path of nodes.
+///
+/// The LDtk entity has these fields:
+/// - `path`: Point array, the nodes visited after the starting position
+/// - `speed`: Float, in pixels per second
+/// - `mode`: Enum, `Loop` or `PingPong`
+/// - `wait`: Float, seconds to stand still at each node
+///
+/// Platforms targeted by a signal source only move while their signal is on.
+#[derive(Clone, Debug, Default, Component)]
+pub struct MovingPlatform {
+    /// Node offsets from the starting position, the first one being the start itself
+    path: Vec<Vec2>,
+    speed: f32,
+    mode: PathMode,
+    wait: Timer,
+    origin: Option<Vec2>,
+    next_node: usize,
+    reversed: bool,
+}
+
+impl From<&EntityInstance> for MovingPlatform {
+    fn from(entity_instance: &EntityInstance) -> MovingPlatform {
+        let start = entity_instance.grid;
+
+        // LDtk grid coordinates grow downwards, bevy ones grow upwards
+        let mut path = vec![Vec2::ZERO];
+        if let Ok(points) = entity_instance.get_points_field("path") {
+            path.extend(points.into_iter().map(|point| {
+                Vec2::new(
+                    (point.x - start.x) as f32 * TILE_SIZE as f32,
+                    (start.y - point.y) as f32 * TILE_SIZE as f32,
+                )
+            }));
+        }
+
+        let mode = match entity_instance.get_enum_field("mode").map(String::as_str) {
+            Ok("Loop") => PathMode::Loop,
+            _ => PathMode::PingPong,
+        };
+
+        let mut wait = Timer::from_seconds(
+            entity_instance
+                .get_float_field("wait")
+                .copied()
+                .unwrap_or(0.5),
+            TimerMode::Once,
+        );
+        // platforms set off right away instead of waiting at their starting position
+        wait.tick(wait.duration());
+
+        MovingPlatform {
+            path,
+            speed: entity_instance
+                .get_float_field("speed")
+                .copied()
+                .unwrap_or(60.),
+            mode,
+            wait,
+            origin: None,
+            next_node: 1,
+            reversed: false,
+        }
+    }
+}
+
+impl MovingPlatform {
+    fn advance(&mut self) {
+        let last_node = self.path.len() - 1;
+        match self.mode {
+            PathMode::Loop => self.next_node = (self.next_node + 1) % self.path.len(),
+            PathMode::PingPong => {
+                if self.next_node == last_node {
+                    self.reversed = true;
+                } else if self.next_node == 0 {
+                    self.reversed = false;
+                }
+
+                if self.reversed {
+                    self.next_node -= 1;
+                } else {
+                    self.next_node += 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct MovingPlatformBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub moving_platform: MovingPlatform,
+    #[from_entity_instance]
+    pub signal_receiver: SignalReceiver,
+    pub rewindable: Rewindable,
+}
+
+pub fn move_platforms(
+    mut platforms: Query<(
+        &mut MovingPlatform,
+        &SignalReceiver,
+        &Transform,
+        &mut Velocity,
+    )>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    if delta_seconds <= 0. {
+        return;
+    }
+
+    for (mut platform, receiver, transform, mut velocity) in &mut platforms {
+        let position = transform.translation.truncate();
+        let origin = *platform.origin.get_or_insert(position);
+
+        let powered = receiver.on || !receiver.wired;
+        if !powered || platform.path.len() < 2 || !platform.wait.tick(time.delta()).finished() {
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        let to_node = origin + platform.path[platform.next_node] - position;
+        if to_node.length() <= platform.speed * delta_seconds {
+            // arrive exactly on the node this frame, then wait there
+            velocity.linvel = to_node / delta_seconds;
+            platform.wait.reset();
+            platform.advance();
+        } else {
+            velocity.linvel = to_node.normalize() * platform.speed;
+        }
+    }
+}
+
+pub struct MovingPlatformPlugin;
+
+impl Plugin for MovingPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform")
+            .add_systems(FixedUpdate, move_platforms.in_set(SimulationSet));
+    }
+}
@@ -0,0 +1,125 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::math::Vector;
+
+use crate::climbing::{Climber, LadderTop};
+use crate::constants::TILE_SIZE;
+use crate::SimulationSet;
+
+/// How thick the plank drawn along the top of a one-way platform is.
+pub const ONE_WAY_PLATFORM_THICKNESS: f32 = 4.;
+
+/// Marks a merged wall collider that only blocks bodies landing on it from above.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct OneWayPlatform;
+
+/// Lets a body fall through one-way platforms while the timer runs.
+#[derive(Clone, Eq, PartialEq, Debug, Component)]
+pub struct DropThrough(Timer);
+
+impl Default for DropThrough {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.25, TimerMode::Once))
+    }
+}
+
+// The state rapier's `update_as_oneway_platform` uses for contacts
+// that stay ignored until the two colliders have separated.
+const CONTACT_CURRENTLY_FORBIDDEN: u32 = 2;
+
+/// Physics hooks that make `OneWayPlatform` colliders solid only from above.
+#[derive(SystemParam)]
+pub struct OneWayPlatformHooks<'w, 's> {
+    platforms: Query<'w, 's, (), With<OneWayPlatform>>,
+    dropping: Query<'w, 's, (), With<DropThrough>>,
+    ladder_tops: Query<'w, 's, (), With<LadderTop>>,
+    climbers: Query<'w, 's, &'static Climber>,
+}
+
+impl BevyPhysicsHooks for OneWayPlatformHooks<'_, '_> {
+    fn modify_solver_contacts(&self, mut context: ContactModificationContextView) {
+        let (collider1, collider2) = (context.collider1(), context.collider2());
+
+        // The allowed normal is expressed in the local space of the first collider,
+        // so it points up out of the platform, or down onto it.
+        let (allowed_normal, platform, other) = if self.platforms.contains(collider1) {
+            (Vector::y(), collider1, collider2)
+        } else if self.platforms.contains(collider2) {
+            (-Vector::y(), collider2, collider1)
+        } else {
+            return;
+        };
+
+        // Climbers pass through the top of a ladder in both directions
+        let climbing_through = self.ladder_tops.contains(platform)
+            && self
+                .climbers
+                .get(other)
+                .is_ok_and(|climber| climber.climbing);
+
+        if self.dropping.contains(other) || climbing_through {
+            context.raw.solver_contacts.clear();
+            *context.raw.user_data = CONTACT_CURRENTLY_FORBIDDEN;
+        } else {
+            context
+                .raw
+                .update_as_oneway_platform(&allowed_normal, FRAC_PI_4);
+        }
+    }
+}
+
+/// The IntGrid layer has no tiles of its own, so every platform gets a plank along its top.
+/// Ladder tops are drawn by the ladder tiles instead.
+#[allow(clippy::type_complexity)]
+pub fn spawn_one_way_platform_sprites(
+    mut commands: Commands,
+    platforms: Query<(Entity, &Collider), (Added<OneWayPlatform>, Without<LadderTop>)>,
+) {
+    for (entity, collider) in &platforms {
+        let half_extents = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents())
+            .unwrap_or(Vec2::splat(TILE_SIZE as f32 / 2.));
+
+        commands.entity(entity).with_children(|platform| {
+            platform.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(0.76, 0.52, 0.41),
+                    custom_size: Some(Vec2::new(half_extents.x * 2., ONE_WAY_PLATFORM_THICKNESS)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(
+                    0.,
+                    half_extents.y - ONE_WAY_PLATFORM_THICKNESS / 2.,
+                    0.,
+                ),
+                ..Default::default()
+            });
+        });
+    }
+}
+
+fn tick_drop_through(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut DropThrough)>,
+    time: Res<Time>,
+) {
+    for (entity, mut drop_through) in &mut query {
+        if drop_through.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<DropThrough>();
+        }
+    }
+}
+
+/// Draws one-way platforms and handles dropping through them.
+/// The platforms themselves are spawned by the `WallPlugin`.
+pub struct OneWayPlatformPlugin;
+
+impl Plugin for OneWayPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_one_way_platform_sprites)
+            .add_systems(FixedUpdate, tick_drop_through.in_set(SimulationSet));
+    }
+}
@@ -4,18 +4,26 @@ use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
 use crate::audio::AudioEvent;
+use crate::characters::{character_select_closed, PlayerCharacter};
 use crate::climbing::Climber;
-use crate::constants::sprites::{
-    fox::{
-        CLIMB_FRAMES, CLIMB_FRAMES_IDLE, IDLE_FRAMES, JUMP_DOWN_FRAMES, JUMP_UP_FRAMES, WALK_FRAMES,
-    },
-    FrameRange,
-};
+use crate::constants::sprites::FrameRange;
 use crate::constants::GAMEPAD_SENSITIVITY_THRESHOLD;
-use crate::utils::is_almost_zero;
+use crate::crates::{Pusher, PUSH_SPEED_FACTOR};
+use crate::gravity::Gravity;
+use crate::health::{Health, Knockback};
+use crate::keys::Inventory;
+use crate::one_way_platforms::DropThrough;
+use crate::slopes::{velocity_along_slope, SLOPE_GRIP_FRICTION};
+use crate::springs::{SpringLaunched, SPRING_CONTROL_ACCELERATION};
+use crate::utils::{is_almost_zero, move_towards};
+use crate::water::{Swimmer, SWIM_SPEED_FACTOR, SWIM_STROKE_VELOCITY};
+use crate::wind::{Windswept, WindsweptBundle};
 use crate::{colliders::ColliderBundle, ground_detection::GroundDetection};
 
-use crate::input::PlayerAction;
+use crate::input::{PlayerAction, PlayerCount};
+use crate::replay::not_waiting_for_replay;
+use crate::rewind::Rewindable;
+use crate::SimulationSet;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct AnimationIndices {
@@ -28,6 +36,13 @@ impl AnimationIndices {
         self.first = frame_range.first;
         self.last = frame_range.last;
     }
+
+    pub fn frame_range(&self) -> FrameRange {
+        FrameRange {
+            first: self.first,
+            last: self.last,
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Component)]
@@ -49,6 +64,8 @@ pub enum Facing {
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Player {
     pub facing: Facing,
+    /// 0 for the player placed in LDtk, counting up for the other local co-op players
+    pub index: usize,
 }
 
 #[derive(Clone, Default, Bundle, LdtkEntity)]
@@ -61,9 +78,19 @@ pub struct PlayerBundle {
     #[from_entity_instance]
     pub collider_bundle: ColliderBundle,
     pub player: Player,
+    pub action_state: ActionState<PlayerAction>,
+    pub input_map: InputMap<PlayerAction>,
+    pub character: PlayerCharacter,
     #[worldly]
     pub worldly: Worldly,
     pub climber: Climber,
+    pub swimmer: Swimmer,
+    pub pusher: Pusher,
+    pub inventory: Inventory,
+    pub health: Health,
+    pub gravity: Gravity,
+    pub windswept: WindsweptBundle,
+    pub rewindable: Rewindable,
     pub ground_detection: GroundDetection,
     // The whole EntityInstance can be stored directly as an EntityInstance component
     #[from_entity_instance]
@@ -115,15 +142,56 @@ fn handle_axis_movement(input: &mut ActionState<PlayerAction>) {
     }
 }
 
-fn player_movement(
-    mut input: Query<&mut ActionState<PlayerAction>, With<Player>>,
-    mut query: Query<(&mut Velocity, &mut Climber, &GroundDetection, &mut Player)>,
+#[allow(clippy::type_complexity)]
+pub fn player_movement(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &ActionState<PlayerAction>,
+        &mut Velocity,
+        &mut Friction,
+        &mut Climber,
+        &Swimmer,
+        &mut Pusher,
+        &Gravity,
+        &Windswept,
+        &GroundDetection,
+        &mut Player,
+        &PlayerCharacter,
+        Has<Knockback>,
+        Has<SpringLaunched>,
+    )>,
     mut audio_event: EventWriter<AudioEvent>,
+    time: Res<Time>,
 ) {
-    let mut input = input.single_mut().to_owned();
-    handle_axis_movement(&mut input);
+    for (
+        entity,
+        action_state,
+        mut velocity,
+        mut friction,
+        mut climber,
+        swimmer,
+        mut pusher,
+        gravity,
+        windswept,
+        ground_detection,
+        mut player,
+        character,
+        knocked_back,
+        spring_launched,
+    ) in &mut query
+    {
+        // being knocked back takes precedence over anything the player does
+        if knocked_back {
+            continue;
+        }
+
+        let mut input = action_state.clone();
+        handle_axis_movement(&mut input);
+
+        // vertical velocities below are relative to gravity, positive being away from the ground
+        let up = gravity.up();
 
-    for (mut velocity, mut climber, ground_detection, mut player) in &mut query {
         let right = if input.pressed(&PlayerAction::Right) {
             player.facing = Facing::Right;
             1.
@@ -137,14 +205,90 @@ fn player_movement(
             0.
         };
 
-        velocity.linvel.x = (right - left) * 200.;
-
         if climber.intersecting_climbables.is_empty() {
             climber.climbing = false;
         } else if input.just_pressed(&PlayerAction::Up) || input.just_pressed(&PlayerAction::Down) {
             climber.climbing = true;
+        } else if ground_detection.on_ground && right != left {
+            // walk off the bottom of a ladder
+            climber.climbing = false;
         }
 
+        pusher.pushing = ground_detection.on_ground
+            && !climber.climbing
+            && ((right > left && pusher.crate_on_right) || (left > right && pusher.crate_on_left));
+
+        let stats = character.0.stats;
+
+        // no sideways movement while on a ladder, climbers are kept centred on it instead
+        let target_velocity_x = if climber.climbing {
+            0.
+        } else if swimmer.swimming {
+            (right - left) * stats.run_speed * SWIM_SPEED_FACTOR
+        } else if pusher.pushing {
+            (right - left) * stats.run_speed * PUSH_SPEED_FACTOR
+        } else {
+            (right - left) * stats.run_speed
+        };
+
+        // Sideways spring launches and slippery ground limit how quickly the player
+        // can change speed, in that order of precedence
+        let control_acceleration =
+            spring_launched
+                .then_some(SPRING_CONTROL_ACCELERATION)
+                .or(ground_detection
+                    .ground_material
+                    .and_then(|material| material.ground_acceleration()));
+
+        // Movement is relative to the ground, so riders keep up with moving platforms,
+        // and the wind blows the player along on top of it, unless they hold on to a ladder
+        let ground_velocity = ground_detection.ground_velocity;
+        let carried_velocity_x = if climber.climbing {
+            ground_velocity.x
+        } else {
+            ground_velocity.x + windswept.drift
+        };
+
+        velocity.linvel.x = carried_velocity_x
+            + match control_acceleration {
+                Some(acceleration) if !climber.climbing => move_towards(
+                    velocity.linvel.x - carried_velocity_x,
+                    target_velocity_x,
+                    acceleration * time.delta_seconds(),
+                ),
+                _ => target_velocity_x,
+            };
+
+        if ground_detection.on_ground && velocity.linvel.y * up <= 0. {
+            // keep up with platforms moving down instead of falling onto them every frame
+            velocity.linvel.y = (velocity.linvel.y * up).min(ground_velocity.y * up) * up;
+        }
+
+        // Follow the slope instead of walking off it into the air when heading downhill,
+        // and grip it when standing still so gravity doesn't drag the player down.
+        // Upside down, the player stands on the flat underside of slope tiles instead
+        let on_slope = ground_detection.slope_normal.filter(|_| {
+            ground_detection.on_ground && !climber.climbing && !swimmer.swimming && up > 0.
+        });
+        if let Some(normal) = on_slope {
+            let slope_velocity_y = velocity_along_slope(velocity.linvel.x, normal);
+            // unless already moving up and away from it, like in the frames right after a jump
+            if velocity.linvel.y <= slope_velocity_y.max(0.) + 1. {
+                velocity.linvel.y = slope_velocity_y;
+            }
+        }
+        *friction = if on_slope.is_some() && is_almost_zero(target_velocity_x) {
+            Friction {
+                coefficient: SLOPE_GRIP_FRICTION,
+                combine_rule: CoefficientCombineRule::Max,
+            }
+        } else {
+            Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            }
+        };
+
         if climber.climbing {
             let up = if input.pressed(&PlayerAction::Up) {
                 1.
@@ -157,41 +301,77 @@ fn player_movement(
                 0.
             };
 
-            velocity.linvel.y = (up - down) * 200.;
+            velocity.linvel.y = (up - down) * stats.climb_speed;
         }
 
         if input.just_pressed(&PlayerAction::Jump)
+            && input.pressed(&PlayerAction::Down)
+            && ground_detection.on_one_way_platform
+            && !climber.climbing
+        {
+            commands.entity(entity).insert(DropThrough::default());
+        } else if input.just_pressed(&PlayerAction::Jump) && swimmer.swimming && !climber.climbing {
+            velocity.linvel.y = SWIM_STROKE_VELOCITY * up;
+        } else if input.just_pressed(&PlayerAction::Jump)
             && (ground_detection.on_ground || climber.climbing)
         {
             audio_event.send(AudioEvent::Jump);
-            velocity.linvel.y = 400.;
+            velocity.linvel.y = stats.jump_velocity * up;
             climber.climbing = false;
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn set_animation(
     mut query: Query<(
         &mut Velocity,
         &mut Climber,
+        &Swimmer,
+        &Pusher,
+        &Gravity,
         &GroundDetection,
         &mut Player,
+        &PlayerCharacter,
         &mut AnimationIndices,
     )>,
 ) {
-    for (velocity, climber, ground_detection, _player, mut animation_indices) in &mut query {
-        let going_up = !ground_detection.on_ground && velocity.linvel.y > 0.;
-        let is_falling =
-            !ground_detection.on_ground && velocity.linvel.y <= 0. && !climber.climbing;
+    for (
+        velocity,
+        climber,
+        swimmer,
+        pusher,
+        gravity,
+        ground_detection,
+        _player,
+        character,
+        mut animation_indices,
+    ) in &mut query
+    {
+        let animations = &character.0.animations;
+        let going_up = !ground_detection.on_ground && velocity.linvel.y * gravity.up() > 0.;
+        let is_falling = !ground_detection.on_ground
+            && velocity.linvel.y * gravity.up() <= 0.
+            && !climber.climbing;
         let is_idle = is_almost_zero(velocity.linvel.x) && is_almost_zero(velocity.linvel.y);
 
-        match (climber.climbing, going_up, is_falling, is_idle) {
-            (true, _, _, true) => animation_indices.set(CLIMB_FRAMES_IDLE),
-            (true, _, _, false) => animation_indices.set(CLIMB_FRAMES),
-            (false, true, _, _) => animation_indices.set(JUMP_UP_FRAMES),
-            (false, false, true, _) => animation_indices.set(JUMP_DOWN_FRAMES),
-            (false, false, false, true) => animation_indices.set(IDLE_FRAMES),
-            (false, false, false, false) => animation_indices.set(WALK_FRAMES),
+        match (
+            climber.climbing,
+            swimmer.swimming,
+            going_up,
+            is_falling,
+            pusher.pushing,
+            is_idle,
+        ) {
+            (true, _, _, _, _, true) => animation_indices.set(animations.climb_idle),
+            (true, _, _, _, _, false) => animation_indices.set(animations.climb),
+            (false, true, _, _, _, true) => animation_indices.set(animations.swim_idle),
+            (false, true, _, _, _, false) => animation_indices.set(animations.swim),
+            (false, false, true, _, _, _) => animation_indices.set(animations.jump_up),
+            (false, false, false, true, _, _) => animation_indices.set(animations.jump_down),
+            (false, false, false, false, true, _) => animation_indices.set(animations.push),
+            (false, false, false, false, false, true) => animation_indices.set(animations.idle),
+            (false, false, false, false, false, false) => animation_indices.set(animations.walk),
         }
     }
 }
@@ -225,6 +405,65 @@ fn animate_player(
     }
 }
 
+/// Local co-op players beyond the first are copies of the one placed in LDtk,
+/// spawned on top of it and sharing its parent so they also persist across levels.
+#[allow(clippy::type_complexity)]
+fn sync_player_count(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    players: Query<(
+        Entity,
+        &Player,
+        &Transform,
+        &Handle<Image>,
+        &TextureAtlas,
+        &EntityInstance,
+        &Parent,
+    )>,
+) {
+    if players.iter().count() == player_count.0 {
+        return;
+    }
+
+    for (entity, player, ..) in &players {
+        if player.index >= player_count.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let Some((_, _, transform, texture, atlas, entity_instance, parent)) =
+        players.iter().find(|(_, player, ..)| player.index == 0)
+    else {
+        return;
+    };
+
+    for index in 1..player_count.0 {
+        if players.iter().any(|(_, player, ..)| player.index == index) {
+            continue;
+        }
+
+        commands.entity(parent.get()).with_children(|world| {
+            world.spawn(PlayerBundle {
+                sprite_sheet_bundle: LdtkSpriteSheetBundle {
+                    sprite_bundle: SpriteBundle {
+                        texture: texture.clone(),
+                        transform: *transform,
+                        ..Default::default()
+                    },
+                    texture_atlas: atlas.clone(),
+                },
+                collider_bundle: ColliderBundle::from(entity_instance),
+                player: Player {
+                    index,
+                    ..Default::default()
+                },
+                entity_instance: entity_instance.clone(),
+                ..Default::default()
+            });
+        });
+    }
+}
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
@@ -232,6 +471,13 @@ impl Plugin for PlayerPlugin {
         app.register_ldtk_entity::<PlayerBundle>("Player")
             .add_systems(Update, set_animation)
             .add_systems(Update, animate_player)
-            .add_systems(Update, player_movement);
+            .add_systems(
+                FixedUpdate,
+                player_movement
+                    .run_if(character_select_closed)
+                    .run_if(not_waiting_for_replay)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, sync_player_count);
     }
 }
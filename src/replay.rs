@@ -0,0 +1,580 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, Lines};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::characters::{CharacterSelect, SelectedCharacter, CHARACTERS};
+use crate::game_flow::{level_index, GameState, NextLevel, RestartLevel, RngSeed};
+use crate::input::{input_map, PlayerAction, PlayerCount, MAX_PLAYERS};
+use crate::player::{player_movement, Player};
+use crate::teleporters::PendingTeleport;
+
+/// Bumped whenever the file layout or the meaning of a recorded tick changes.
+pub const REPLAY_VERSION: u32 = 1;
+const REPLAY_HEADER: &str = "my-game replay";
+pub const REPLAY_DIRECTORY: &str = "replays";
+
+/// The buttons stored in `RecordedInput::pressed`, one bit each in this order.
+const RECORDED_BUTTONS: [PlayerAction; 6] = [
+    PlayerAction::Up,
+    PlayerAction::Down,
+    PlayerAction::Left,
+    PlayerAction::Right,
+    PlayerAction::Jump,
+    PlayerAction::Rewind,
+];
+
+/// One player's input during one fixed tick.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct RecordedInput {
+    pub pressed: u8,
+    pub movement: Vec2,
+}
+
+impl RecordedInput {
+    fn capture(action_state: &ActionState<PlayerAction>) -> RecordedInput {
+        let pressed = RECORDED_BUTTONS
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| action_state.pressed(action))
+            .fold(0, |pressed, (bit, _)| pressed | 1 << bit);
+
+        RecordedInput {
+            pressed,
+            movement: action_state.axis_pair(&PlayerAction::Move),
+        }
+    }
+
+    /// Pressing an already pressed button keeps it held, so `just_pressed` is reproduced
+    /// on the same ticks it happened on while recording.
+    fn apply(&self, action_state: &mut ActionState<PlayerAction>) {
+        for (bit, action) in RECORDED_BUTTONS.iter().enumerate() {
+            if self.pressed & 1 << bit != 0 {
+                action_state.press(action);
+            } else {
+                action_state.release(action);
+            }
+        }
+        action_state.set_axis_pair(&PlayerAction::Move, self.movement);
+    }
+}
+
+/// Everything needed to play a run back: where it started and what every player pressed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    /// The iid of the level, which stays the same when levels are renamed or reordered
+    pub level_iid: String,
+    pub seed: u32,
+    pub character: usize,
+    pub player_count: usize,
+    /// One entry per fixed tick, holding the input of each player in index order
+    pub ticks: Vec<Vec<RecordedInput>>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    Malformed(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{error}"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay version {version} is not supported, expected {REPLAY_VERSION}"
+            ),
+            ReplayError::Malformed(reason) => write!(f, "malformed replay: {reason}"),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{REPLAY_HEADER} {REPLAY_VERSION}")?;
+        writeln!(f, "level {}", self.level_iid)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "character {}", self.character)?;
+        writeln!(f, "players {}", self.player_count)?;
+        writeln!(f, "ticks {}", self.ticks.len())?;
+        for tick in &self.ticks {
+            let inputs: Vec<String> = tick
+                .iter()
+                .map(|input| {
+                    format!(
+                        "{} {} {}",
+                        input.pressed, input.movement.x, input.movement.y
+                    )
+                })
+                .collect();
+            writeln!(f, "{}", inputs.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+fn header_field<'a>(lines: &mut Lines<'a>, name: &str) -> Result<&'a str, ReplayError> {
+    lines
+        .next()
+        .and_then(|line| line.strip_prefix(name))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| ReplayError::Malformed(format!("missing {name}")))
+}
+
+fn parse_header_field<T: FromStr>(lines: &mut Lines, name: &str) -> Result<T, ReplayError> {
+    header_field(lines, name)?
+        .parse()
+        .map_err(|_| ReplayError::Malformed(format!("invalid {name}")))
+}
+
+impl Replay {
+    /// Reads the replay format written by `Display`.
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let malformed = |reason: &str| ReplayError::Malformed(reason.to_string());
+        let mut lines = text.lines();
+
+        let version = lines
+            .next()
+            .and_then(|line| line.strip_prefix(REPLAY_HEADER))
+            .ok_or_else(|| malformed("missing header"))?
+            .trim()
+            .parse()
+            .map_err(|_| malformed("invalid version"))?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let level_iid = header_field(&mut lines, "level")?.to_string();
+        let seed = parse_header_field(&mut lines, "seed")?;
+        let character = parse_header_field(&mut lines, "character")?;
+        let player_count = parse_header_field(&mut lines, "players")?;
+        let tick_count = parse_header_field(&mut lines, "ticks")?;
+
+        if character >= CHARACTERS.len() {
+            return Err(malformed("unknown character"));
+        }
+        if player_count == 0 || player_count > MAX_PLAYERS {
+            return Err(malformed("unsupported player count"));
+        }
+
+        let ticks = lines
+            .take(tick_count)
+            .map(|line| {
+                let values: Vec<&str> = line.split_whitespace().collect();
+                if values.len() != player_count * 3 {
+                    return Err(malformed("wrong number of inputs in a tick"));
+                }
+                values
+                    .chunks(3)
+                    .map(|input| {
+                        Some(RecordedInput {
+                            pressed: input[0].parse().ok()?,
+                            movement: Vec2::new(input[1].parse().ok()?, input[2].parse().ok()?),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| malformed("invalid input"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if ticks.len() != tick_count {
+            return Err(malformed("fewer ticks than declared"));
+        }
+
+        Ok(Replay {
+            level_iid,
+            seed,
+            character,
+            player_count,
+            ticks,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        Replay::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the replay to a new file in `REPLAY_DIRECTORY`, returning its path.
+    /// Never overwrites an earlier replay: a counter is appended when the name is taken.
+    pub fn save(&self) -> Result<PathBuf, ReplayError> {
+        fs::create_dir_all(REPLAY_DIRECTORY)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        for attempt in 0.. {
+            let name = if attempt == 0 {
+                format!("replay-{timestamp}.replay")
+            } else {
+                format!("replay-{timestamp}-{attempt}.replay")
+            };
+            let path = Path::new(REPLAY_DIRECTORY).join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(self.to_string().as_bytes())?;
+                    return Ok(path);
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        unreachable!("ran out of replay file names")
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum ReplayMode {
+    #[default]
+    Idle,
+    Recording(Replay),
+    Playing {
+        replay: Replay,
+        tick: usize,
+    },
+}
+
+/// F5 starts and stops recording, F6 plays back the last recording.
+/// A replay file can also be played on startup with `--replay <path>`.
+#[derive(Clone, PartialEq, Debug, Default, Resource)]
+pub struct ReplayState {
+    pub mode: ReplayMode,
+    /// Set while the world respawns for a new recording or playback, during which no ticks count
+    pub waiting_for_level: bool,
+    pub last_recording: Option<PathBuf>,
+}
+
+pub fn not_waiting_for_replay(replay_state: Res<ReplayState>) -> bool {
+    !replay_state.waiting_for_level
+}
+
+pub fn not_playing_replay(replay_state: Res<ReplayState>) -> bool {
+    !matches!(replay_state.mode, ReplayMode::Playing { .. })
+}
+
+fn replay_path_from_args() -> Option<PathBuf> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay");
+    args.next()?;
+    args.next().map(PathBuf::from)
+}
+
+fn save_recording(replay: &Replay, replay_state: &mut ReplayState) {
+    match replay.save() {
+        Ok(path) => {
+            info!("saved replay to {}", path.display());
+            replay_state.last_recording = Some(path);
+        }
+        Err(error) => error!("failed to save replay: {error}"),
+    }
+}
+
+/// Both recording and playback start from a freshly spawned and restarted world,
+/// so every run begins from the same state.
+#[allow(clippy::too_many_arguments)]
+fn replay_controls(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut replay_state: ResMut<ReplayState>,
+    mut args_checked: Local<bool>,
+    mut game_state: ResMut<GameState>,
+    mut rng_seed: ResMut<RngSeed>,
+    mut player_count: ResMut<PlayerCount>,
+    mut selected_character: ResMut<SelectedCharacter>,
+    mut character_select: ResMut<CharacterSelect>,
+    mut restart_level_writer: EventWriter<RestartLevel>,
+    worlds: Query<(Entity, &Handle<LdtkProject>)>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    // levels are looked up by iid, so nothing can start before the project has loaded
+    let Ok((world, ldtk_handle)) = worlds.get_single() else {
+        return;
+    };
+    let Some(ldtk_project) = ldtk_project_assets.get(ldtk_handle) else {
+        return;
+    };
+
+    let mut play = None;
+    if !*args_checked {
+        *args_checked = true;
+        play = replay_path_from_args();
+    }
+
+    let mode = std::mem::take(&mut replay_state.mode);
+    let (replay, playing) = match mode {
+        ReplayMode::Recording(replay) if keyboard_input.just_pressed(KeyCode::F5) => {
+            save_recording(&replay, &mut replay_state);
+            return;
+        }
+        ReplayMode::Playing { .. }
+            if keyboard_input.any_just_pressed([KeyCode::F5, KeyCode::F6]) =>
+        {
+            info!("replay stopped");
+            return;
+        }
+        ReplayMode::Idle if keyboard_input.just_pressed(KeyCode::F5) => {
+            let Some(level) = ldtk_project
+                .find_raw_level_by_level_selection(&game_state.current_level_identifier())
+            else {
+                error!("can't record, the current level is not in the project");
+                return;
+            };
+            let replay = Replay {
+                level_iid: level.iid.clone(),
+                seed: rng_seed.0,
+                character: selected_character.0,
+                player_count: player_count.0,
+                ticks: Vec::new(),
+            };
+            (replay, false)
+        }
+        ReplayMode::Idle => {
+            if keyboard_input.just_pressed(KeyCode::F6) {
+                play = replay_state.last_recording.clone();
+            }
+            let Some(path) = play else {
+                return;
+            };
+            match Replay::load(&path) {
+                Ok(replay) => (replay, true),
+                Err(error) => {
+                    error!("failed to load replay {}: {error}", path.display());
+                    return;
+                }
+            }
+        }
+        mode => {
+            replay_state.mode = mode;
+            return;
+        }
+    };
+
+    let Some(level_index) = level_index(ldtk_project, &replay.level_iid) else {
+        error!("replay level {} is not in the project", replay.level_iid);
+        return;
+    };
+
+    game_state.current_level = level_index;
+    commands.insert_resource(game_state.current_level_identifier());
+    rng_seed.0 = replay.seed;
+    selected_character.0 = replay.character;
+    character_select.open = false;
+    if player_count.0 != replay.player_count {
+        player_count.0 = replay.player_count;
+    }
+    commands.entity(world).insert(Respawn);
+    // respawning only resets entities, the restart resets the rest of the level's state
+    restart_level_writer.send(RestartLevel);
+    commands.insert_resource(PendingTeleport::default());
+
+    replay_state.waiting_for_level = true;
+    replay_state.mode = if playing {
+        info!("playing replay");
+        ReplayMode::Playing { replay, tick: 0 }
+    } else {
+        info!("recording replay");
+        ReplayMode::Recording(replay)
+    };
+}
+
+fn wait_for_level(
+    mut replay_state: ResMut<ReplayState>,
+    mut level_events: EventReader<LevelEvent>,
+) {
+    let transformed = level_events
+        .read()
+        .any(|event| matches!(event, LevelEvent::Transformed(_)));
+    if transformed && replay_state.waiting_for_level {
+        replay_state.waiting_for_level = false;
+    }
+}
+
+fn record_inputs(
+    mut replay_state: ResMut<ReplayState>,
+    players: Query<(&Player, &ActionState<PlayerAction>)>,
+) {
+    if replay_state.waiting_for_level {
+        return;
+    }
+    let ReplayMode::Recording(replay) = &mut replay_state.mode else {
+        return;
+    };
+
+    let mut inputs = vec![RecordedInput::default(); replay.player_count];
+    for (player, action_state) in &players {
+        if let Some(input) = inputs.get_mut(player.index) {
+            *input = RecordedInput::capture(action_state);
+        }
+    }
+    replay.ticks.push(inputs);
+}
+
+/// Players are cut off from their devices while a replay drives them,
+/// and get their bindings back once it ends.
+#[allow(clippy::type_complexity)]
+fn play_inputs(
+    mut commands: Commands,
+    mut replay_state: ResMut<ReplayState>,
+    player_count: Res<PlayerCount>,
+    mut players: Query<(
+        Entity,
+        &Player,
+        &mut ActionState<PlayerAction>,
+        Has<InputMap<PlayerAction>>,
+    )>,
+) {
+    let waiting_for_level = replay_state.waiting_for_level;
+    let ReplayMode::Playing { replay, tick } = &mut replay_state.mode else {
+        return;
+    };
+
+    if let Some(inputs) = replay.ticks.get(*tick) {
+        for (entity, player, mut action_state, has_input_map) in &mut players {
+            if has_input_map {
+                commands.entity(entity).remove::<InputMap<PlayerAction>>();
+            }
+            if let (false, Some(input)) = (waiting_for_level, inputs.get(player.index)) {
+                input.apply(&mut action_state);
+            }
+        }
+        if !waiting_for_level {
+            *tick += 1;
+        }
+        return;
+    }
+
+    info!("replay finished");
+    replay_state.mode = ReplayMode::Idle;
+    for (entity, player, _, _) in &players {
+        commands
+            .entity(entity)
+            .insert(input_map(player.index, player_count.0, None));
+    }
+}
+
+/// Reaching the goal ends the run, reporting its time so it can be checked against a replay.
+fn finish_replay_on_level_complete(
+    mut next_level_reader: EventReader<NextLevel>,
+    mut replay_state: ResMut<ReplayState>,
+    time: Res<Time<Fixed>>,
+) {
+    if next_level_reader.read().count() == 0 {
+        return;
+    }
+
+    match std::mem::take(&mut replay_state.mode) {
+        ReplayMode::Recording(replay) => {
+            let seconds = replay.ticks.len() as f32 * time.timestep().as_secs_f32();
+            info!("level {} finished in {seconds:.3}s", replay.level_iid);
+            save_recording(&replay, &mut replay_state);
+        }
+        ReplayMode::Playing { replay, tick } => {
+            let seconds = tick as f32 * time.timestep().as_secs_f32();
+            info!(
+                "replay finished level {} in {seconds:.3}s",
+                replay.level_iid
+            );
+            // hand control back on the next tick
+            replay_state.mode = ReplayMode::Playing {
+                tick: replay.ticks.len(),
+                replay,
+            };
+        }
+        ReplayMode::Idle => (),
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayState>()
+            .add_systems(Update, replay_controls)
+            .add_systems(Update, wait_for_level.after(replay_controls))
+            .add_systems(Update, finish_replay_on_level_complete)
+            .add_systems(FixedUpdate, play_inputs.before(player_movement))
+            .add_systems(FixedUpdate, record_inputs.after(play_inputs));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        Replay {
+            level_iid: "a2b3c4d0-66b0-11ec-9cd7-c721746049b9".to_string(),
+            seed: u32::MAX,
+            character: 1,
+            player_count: 2,
+            ticks: vec![
+                vec![
+                    RecordedInput::default(),
+                    RecordedInput {
+                        pressed: 0b10001,
+                        movement: Vec2::new(-0.25, 1.),
+                    },
+                ],
+                vec![
+                    RecordedInput {
+                        pressed: 0b111111,
+                        movement: Vec2::new(0.1, -0.7),
+                    },
+                    RecordedInput::default(),
+                ],
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = replay();
+        assert_eq!(Replay::parse(&replay.to_string()).unwrap(), replay);
+    }
+
+    #[test]
+    fn wrong_header() {
+        let text = replay()
+            .to_string()
+            .replacen(REPLAY_HEADER, "not a replay", 1);
+        assert!(matches!(
+            Replay::parse(&text),
+            Err(ReplayError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_version() {
+        let text = replay().to_string().replacen(
+            &format!("{REPLAY_HEADER} {REPLAY_VERSION}"),
+            &format!("{REPLAY_HEADER} {}", REPLAY_VERSION + 1),
+            1,
+        );
+        assert!(matches!(
+            Replay::parse(&text),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn short_tick_list() {
+        let text = replay().to_string();
+        let truncated: Vec<&str> = text.lines().take(text.lines().count() - 1).collect();
+        assert!(matches!(
+            Replay::parse(&truncated.join("\n")),
+            Err(ReplayError::Malformed(_))
+        ));
+    }
+}
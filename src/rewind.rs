@@ -0,0 +1,356 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::characters::CharacterSelect;
+use crate::climbing::Climber;
+use crate::crumble::CrumbleTile;
+use crate::enemies::Enemy;
+use crate::game_flow::RestartLevel;
+use crate::ground_detection::GroundDetection;
+use crate::health::Health;
+use crate::input::{PlayerAction, PlayerCount};
+use crate::keys::{Inventory, Key, LockedDoor};
+use crate::logic::{SignalReceiver, SignalSource, Spawner};
+use crate::moving_platforms::MovingPlatform;
+use crate::player::AnimationIndices;
+use crate::switches::SwitchState;
+use crate::turrets::{Projectile, Turret};
+use crate::SimulationSet;
+
+#[derive(Copy, Clone, PartialEq, Debug, Resource)]
+pub struct RewindSettings {
+    /// How far back time can be rewound
+    pub seconds: f32,
+}
+
+impl Default for RewindSettings {
+    fn default() -> Self {
+        RewindSettings { seconds: 5. }
+    }
+}
+
+impl RewindSettings {
+    /// One snapshot is recorded per fixed tick.
+    fn capacity(&self, timestep: Duration) -> usize {
+        (self.seconds / timestep.as_secs_f32()).ceil() as usize
+    }
+}
+
+/// Entities whose state is recorded every fixed tick and restored when rewinding.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Rewindable;
+
+/// A body's movement during one tick.
+///
+/// Contacts like `Climber::intersecting_climbables` are left to the physics events,
+/// which catch up as soon as time moves forward again.
+#[derive(Clone)]
+struct Snapshot {
+    transform: Transform,
+    velocity: Velocity,
+    climbing: Option<bool>,
+    ground_detection: Option<GroundDetection>,
+    animation: Option<(AnimationIndices, usize)>,
+}
+
+/// Everything recorded during one tick, apart from the components in a `ComponentHistory`.
+#[derive(Clone)]
+struct Frame {
+    /// Every `Rewindable` entity that existed, so ones spawned later can be removed again
+    entities: HashSet<Entity>,
+    bodies: HashMap<Entity, Snapshot>,
+    disabled_colliders: HashSet<Entity>,
+    switch_state: SwitchState,
+}
+
+/// The most recent ticks, oldest first, holding at most `RewindSettings::capacity` of them.
+#[derive(Clone, Default, Resource)]
+pub struct RewindBuffer {
+    frames: VecDeque<Frame>,
+}
+
+/// The values of one component on `Rewindable` entities, a tick per entry of `RewindBuffer`.
+#[derive(Clone, Resource)]
+struct ComponentHistory<C: Component + Clone> {
+    ticks: VecDeque<Vec<(Entity, C)>>,
+}
+
+impl<C: Component + Clone> Default for ComponentHistory<C> {
+    fn default() -> Self {
+        ComponentHistory {
+            ticks: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Resource)]
+pub struct Rewinding(pub bool);
+
+pub fn not_rewinding(rewinding: Res<Rewinding>) -> bool {
+    !rewinding.0
+}
+
+/// Rewinding is held by any player, and is unavailable while choosing a character.
+fn update_rewinding(
+    input: Query<&ActionState<PlayerAction>>,
+    character_select: Res<CharacterSelect>,
+    rewind_buffer: Res<RewindBuffer>,
+    mut rewinding: ResMut<Rewinding>,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+) {
+    let held = input
+        .iter()
+        .any(|input| input.pressed(&PlayerAction::Rewind));
+    let rewind = held && !character_select.open && !rewind_buffer.frames.is_empty();
+
+    if rewinding.0 != rewind {
+        rewinding.0 = rewind;
+        // the simulation stands still while it is being scrubbed through
+        rapier_configuration.physics_pipeline_active = !rewind;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn record_snapshots(
+    settings: Res<RewindSettings>,
+    time: Res<Time<Fixed>>,
+    switch_state: Res<SwitchState>,
+    mut rewind_buffer: ResMut<RewindBuffer>,
+    rewindables: Query<(Entity, Has<ColliderDisabled>), With<Rewindable>>,
+    bodies: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            Option<&Climber>,
+            Option<&GroundDetection>,
+            Option<(&AnimationIndices, &TextureAtlas)>,
+        ),
+        With<Rewindable>,
+    >,
+) {
+    let frame = Frame {
+        entities: rewindables.iter().map(|(entity, _)| entity).collect(),
+        bodies: bodies
+            .iter()
+            .map(
+                |(entity, transform, velocity, climber, ground_detection, animation)| {
+                    (
+                        entity,
+                        Snapshot {
+                            transform: *transform,
+                            velocity: *velocity,
+                            climbing: climber.map(|climber| climber.climbing),
+                            ground_detection: ground_detection.cloned(),
+                            animation: animation.map(|(indices, atlas)| (*indices, atlas.index)),
+                        },
+                    )
+                },
+            )
+            .collect(),
+        disabled_colliders: rewindables
+            .iter()
+            .filter(|(_, disabled)| *disabled)
+            .map(|(entity, _)| entity)
+            .collect(),
+        switch_state: *switch_state,
+    };
+
+    rewind_buffer.frames.push_back(frame);
+    while rewind_buffer.frames.len() > settings.capacity(time.timestep()) {
+        rewind_buffer.frames.pop_front();
+    }
+}
+
+/// Entities that no longer exist are skipped, and ones spawned since, like projectiles
+/// and spawned crates, are despawned.
+#[allow(clippy::type_complexity)]
+fn restore_snapshots(
+    mut commands: Commands,
+    mut rewind_buffer: ResMut<RewindBuffer>,
+    mut switch_state: ResMut<SwitchState>,
+    rewindables: Query<(Entity, Has<ColliderDisabled>), With<Rewindable>>,
+    mut bodies: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            Option<&mut Climber>,
+            Option<&mut GroundDetection>,
+            Option<(&mut AnimationIndices, &mut TextureAtlas)>,
+        ),
+        With<Rewindable>,
+    >,
+) {
+    let Some(frame) = rewind_buffer.frames.pop_back() else {
+        return;
+    };
+
+    for (entity, disabled) in &rewindables {
+        if !frame.entities.contains(&entity) {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let was_disabled = frame.disabled_colliders.contains(&entity);
+        if was_disabled && !disabled {
+            commands.entity(entity).insert(ColliderDisabled);
+        } else if !was_disabled && disabled {
+            commands.entity(entity).remove::<ColliderDisabled>();
+        }
+    }
+
+    if *switch_state != frame.switch_state {
+        *switch_state = frame.switch_state;
+    }
+
+    for (entity, snapshot) in frame.bodies {
+        let Ok((mut transform, mut velocity, climber, ground_detection, animation)) =
+            bodies.get_mut(entity)
+        else {
+            continue;
+        };
+
+        *transform = snapshot.transform;
+        *velocity = snapshot.velocity;
+        if let (Some(mut climber), Some(climbing)) = (climber, snapshot.climbing) {
+            climber.climbing = climbing;
+        }
+        if let (Some(mut ground_detection), Some(snapshot)) =
+            (ground_detection, snapshot.ground_detection)
+        {
+            *ground_detection = snapshot;
+        }
+        if let (Some((mut indices, mut atlas)), Some((snapshot_indices, snapshot_index))) =
+            (animation, snapshot.animation)
+        {
+            *indices = snapshot_indices;
+            atlas.index = snapshot_index;
+        }
+    }
+}
+
+/// Kept in step with `RewindBuffer`, trimmed to as many ticks as it holds.
+fn record_component<C: Component + Clone>(
+    rewind_buffer: Res<RewindBuffer>,
+    mut history: ResMut<ComponentHistory<C>>,
+    components: Query<(Entity, &C), With<Rewindable>>,
+) {
+    history.ticks.push_back(
+        components
+            .iter()
+            .map(|(entity, component)| (entity, component.clone()))
+            .collect(),
+    );
+    while history.ticks.len() > rewind_buffer.frames.len() {
+        history.ticks.pop_front();
+    }
+}
+
+fn restore_component<C: Component + Clone>(
+    rewind_buffer: Res<RewindBuffer>,
+    mut history: ResMut<ComponentHistory<C>>,
+    mut components: Query<&mut C, With<Rewindable>>,
+) {
+    let mut restored = None;
+    while history.ticks.len() > rewind_buffer.frames.len() {
+        restored = history.ticks.pop_back();
+    }
+
+    for (entity, snapshot) in restored.into_iter().flatten() {
+        if let Ok(mut component) = components.get_mut(entity) {
+            *component = snapshot;
+        }
+    }
+}
+
+/// Records the component on `Rewindable` entities every tick, and restores it when rewinding.
+fn rewind_component<C: Component + Clone>(app: &mut App) {
+    app.init_resource::<ComponentHistory<C>>()
+        .add_systems(
+            FixedUpdate,
+            record_component::<C>
+                .after(record_snapshots)
+                .before(SimulationSet)
+                .run_if(not_rewinding),
+        )
+        .add_systems(
+            FixedUpdate,
+            restore_component::<C>
+                .after(restore_snapshots)
+                .run_if(is_rewinding),
+        );
+}
+
+/// Snapshots from before a restart, a level change or a player joining can't be rewound to,
+/// so the buffer starts over after every transition.
+fn clear_on_transitions(
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut level_events: EventReader<LevelEvent>,
+    player_count: Res<PlayerCount>,
+    mut rewind_buffer: ResMut<RewindBuffer>,
+) {
+    let restarted = restart_level_reader.read().count() > 0;
+    let level_changed = level_events.read().count() > 0;
+    if restarted || level_changed || player_count.is_changed() {
+        rewind_buffer.frames.clear();
+    }
+}
+
+pub fn is_rewinding(rewinding: Res<Rewinding>) -> bool {
+    rewinding.0
+}
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RewindSettings>()
+            .init_resource::<RewindBuffer>()
+            .init_resource::<Rewinding>()
+            // the simulation stands still while rewinding
+            .configure_sets(FixedUpdate, SimulationSet.run_if(not_rewinding))
+            .add_systems(FixedUpdate, clear_on_transitions)
+            .add_systems(
+                FixedUpdate,
+                update_rewinding
+                    .after(clear_on_transitions)
+                    .before(SimulationSet),
+            )
+            // the state at the start of the tick, as the last physics step left it
+            .add_systems(
+                FixedUpdate,
+                record_snapshots
+                    .after(update_rewinding)
+                    .before(SimulationSet)
+                    .run_if(not_rewinding),
+            )
+            .add_systems(
+                FixedUpdate,
+                restore_snapshots
+                    .after(update_rewinding)
+                    .run_if(is_rewinding),
+            );
+
+        rewind_component::<Visibility>(app);
+        rewind_component::<Health>(app);
+        rewind_component::<Inventory>(app);
+        rewind_component::<Key>(app);
+        rewind_component::<LockedDoor>(app);
+        rewind_component::<SignalSource>(app);
+        rewind_component::<SignalReceiver>(app);
+        rewind_component::<Spawner>(app);
+        rewind_component::<MovingPlatform>(app);
+        rewind_component::<CrumbleTile>(app);
+        rewind_component::<Enemy>(app);
+        rewind_component::<Turret>(app);
+        rewind_component::<Projectile>(app);
+    }
+}
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{ColorMesh2dBundle, Mesh2dHandle};
+use bevy_rapier2d::prelude::*;
+
+use crate::constants::TILE_SIZE;
+
+/// Friction applied to walkers standing still on a slope, so that gravity can't drag them down it.
+pub const SLOPE_GRIP_FRICTION: f32 = 2.0;
+/// How much of a tile a gentle slope rises over one tile, tan 22.5°.
+pub const GENTLE_SLOPE_RISE: f32 = std::f32::consts::SQRT_2 - 1.;
+
+/// The shape of a slope tile, named after the direction it rises in.
+///
+/// Steep slopes rise a whole tile over one tile (45°).
+/// Gentle slopes rise `GENTLE_SLOPE_RISE` of a tile per tile (22.5°), over a low and a high tile.
+/// The pair falls short of a whole tile, so the high end meets the next tile at a small step,
+/// which the player's rounded corners ride over.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Component)]
+pub enum Slope {
+    UpRight,
+    UpLeft,
+    GentleUpRightLow,
+    GentleUpRightHigh,
+    GentleUpLeftLow,
+    GentleUpLeftHigh,
+}
+
+impl Slope {
+    /// The outline of the solid part of the tile, relative to the tile's centre.
+    fn vertices(&self, half_tile: f32) -> Vec<Vec2> {
+        let h = half_tile;
+        // the height of a gentle slope's surface where its low and high tiles meet, and at its top
+        let middle = -h + 2. * h * GENTLE_SLOPE_RISE;
+        let high = -h + 4. * h * GENTLE_SLOPE_RISE;
+        match self {
+            Slope::UpRight => vec![Vec2::new(-h, -h), Vec2::new(h, -h), Vec2::new(h, h)],
+            Slope::UpLeft => vec![Vec2::new(-h, -h), Vec2::new(h, -h), Vec2::new(-h, h)],
+            Slope::GentleUpRightLow => {
+                vec![Vec2::new(-h, -h), Vec2::new(h, -h), Vec2::new(h, middle)]
+            }
+            Slope::GentleUpRightHigh => vec![
+                Vec2::new(-h, -h),
+                Vec2::new(h, -h),
+                Vec2::new(h, high),
+                Vec2::new(-h, middle),
+            ],
+            Slope::GentleUpLeftLow => {
+                vec![Vec2::new(-h, -h), Vec2::new(h, -h), Vec2::new(-h, middle)]
+            }
+            Slope::GentleUpLeftHigh => vec![
+                Vec2::new(-h, -h),
+                Vec2::new(h, -h),
+                Vec2::new(h, middle),
+                Vec2::new(-h, high),
+            ],
+        }
+    }
+
+    /// A mesh of the same outline as the collider, to draw the tile with.
+    pub fn mesh(&self, half_tile: f32) -> Mesh {
+        let vertices = self.vertices(half_tile);
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| [v.x, v.y, 0.]).collect();
+        // the outline is convex, so it splits into a fan of triangles around its first vertex
+        let indices = (1..vertices.len() as u32 - 1)
+            .flat_map(|i| [0, i, i + 1])
+            .collect();
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    pub fn collider(&self, half_tile: f32) -> Collider {
+        Collider::convex_hull(&self.vertices(half_tile))
+            .expect("Slope vertices should form a convex polygon")
+    }
+
+    /// The unit normal of the walkable surface.
+    pub fn normal(&self) -> Vec2 {
+        match self {
+            Slope::UpRight => Vec2::new(-1., 1.),
+            Slope::UpLeft => Vec2::new(1., 1.),
+            Slope::GentleUpRightLow | Slope::GentleUpRightHigh => Vec2::new(-GENTLE_SLOPE_RISE, 1.),
+            Slope::GentleUpLeftLow | Slope::GentleUpLeftHigh => Vec2::new(GENTLE_SLOPE_RISE, 1.),
+        }
+        .normalize()
+    }
+}
+
+/// The vertical velocity that keeps a body moving at `velocity_x` on the surface with `normal`.
+pub fn velocity_along_slope(velocity_x: f32, normal: Vec2) -> f32 {
+    -velocity_x * normal.x / normal.y
+}
+
+/// The IntGrid layer has no tiles of its own, so every slope is drawn in the shape of its collider.
+pub fn spawn_slope_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    slopes: Query<(Entity, &Slope), Added<Slope>>,
+) {
+    for (entity, slope) in &slopes {
+        let mesh = meshes.add(slope.mesh(TILE_SIZE as f32 / 2.));
+        let material = materials.add(Color::srgb(0.56, 0.34, 0.23));
+
+        commands.entity(entity).with_children(|slope| {
+            slope.spawn(ColorMesh2dBundle {
+                mesh: Mesh2dHandle(mesh),
+                material,
+                ..Default::default()
+            });
+        });
+    }
+}
+
+/// Draws slope tiles. Their colliders are spawned by the `WallPlugin`.
+pub struct SlopePlugin;
+
+impl Plugin for SlopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_slope_meshes);
+    }
+}
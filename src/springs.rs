@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::AudioEvent;
+use crate::climbing::Climber;
+use crate::colliders::SensorBundle;
+use crate::player::player_movement;
+use crate::SimulationSet;
+
+/// How long a spring shows its compressed frame after launching something.
+pub const SPRING_COMPRESSION_SECONDS: f32 = 0.2;
+/// How long a sideways launch limits how quickly the player can steer against it.
+pub const SPRING_LAUNCH_SECONDS: f32 = 0.4;
+/// The player's horizontal acceleration while `SpringLaunched`, in pixels per second squared.
+pub const SPRING_CONTROL_ACCELERATION: f32 = 600.;
+
+/// Launches any dynamic body touching it.
+///
+/// The LDtk entity has these fields:
+/// - `direction`: Enum, `Up`, `Down`, `Left` or `Right`
+/// - `strength`: Float, the launch speed in pixels per second
+///
+/// The compressed frame is expected right after the spring's tile in its tileset.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Spring {
+    pub direction: Vec2,
+    pub strength: f32,
+    compression: Timer,
+    rest_frame: Option<usize>,
+}
+
+impl From<&EntityInstance> for Spring {
+    fn from(entity_instance: &EntityInstance) -> Spring {
+        let direction = match entity_instance
+            .get_enum_field("direction")
+            .map(String::as_str)
+        {
+            Ok("Down") => Vec2::NEG_Y,
+            Ok("Left") => Vec2::NEG_X,
+            Ok("Right") => Vec2::X,
+            _ => Vec2::Y,
+        };
+
+        let mut compression = Timer::from_seconds(SPRING_COMPRESSION_SECONDS, TimerMode::Once);
+        compression.tick(compression.duration());
+
+        Spring {
+            direction,
+            strength: entity_instance
+                .get_float_field("strength")
+                .copied()
+                .unwrap_or(600.),
+            compression,
+            rest_frame: None,
+        }
+    }
+}
+
+/// Keeps a sideways launch from being cancelled straight away by the player's own movement.
+#[derive(Clone, Eq, PartialEq, Debug, Component)]
+pub struct SpringLaunched(Timer);
+
+impl Default for SpringLaunched {
+    fn default() -> Self {
+        SpringLaunched(Timer::from_seconds(SPRING_LAUNCH_SECONDS, TimerMode::Once))
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SpringBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub spring: Spring,
+}
+
+/// Like a jump, the launch replaces the velocity along the spring's direction
+/// and keeps the rest, but it works whether or not the body is on the ground.
+pub fn launch_from_springs(
+    mut commands: Commands,
+    mut springs: Query<&mut Spring>,
+    mut bodies: Query<(&RigidBody, &mut Velocity, Option<&mut Climber>)>,
+    mut collisions: EventReader<CollisionEvent>,
+    mut audio_event: EventWriter<AudioEvent>,
+) {
+    for collision in collisions.read() {
+        if let CollisionEvent::Started(collider_a, collider_b, _) = collision {
+            for (spring_entity, body) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                let Ok(mut spring) = springs.get_mut(*spring_entity) else {
+                    continue;
+                };
+                let Ok((RigidBody::Dynamic, mut velocity, climber)) = bodies.get_mut(*body) else {
+                    continue;
+                };
+
+                let direction = spring.direction;
+                velocity.linvel += direction * (spring.strength - velocity.linvel.dot(direction));
+                if let Some(mut climber) = climber {
+                    climber.climbing = false;
+                }
+                if direction.x != 0. {
+                    commands.entity(*body).insert(SpringLaunched::default());
+                }
+
+                spring.compression.reset();
+                audio_event.send(AudioEvent::Spring);
+            }
+        }
+    }
+}
+
+pub fn tick_spring_launches(
+    mut commands: Commands,
+    mut launched: Query<(Entity, &mut SpringLaunched)>,
+    time: Res<Time>,
+) {
+    for (entity, mut launch) in &mut launched {
+        if launch.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<SpringLaunched>();
+        }
+    }
+}
+
+pub fn animate_springs(mut springs: Query<(&mut Spring, &mut TextureAtlas)>, time: Res<Time>) {
+    for (mut spring, mut atlas) in &mut springs {
+        let rest_frame = *spring.rest_frame.get_or_insert(atlas.index);
+        let compressed = !spring.compression.tick(time.delta()).finished();
+        let frame = if compressed {
+            rest_frame + 1
+        } else {
+            rest_frame
+        };
+        if atlas.index != frame {
+            atlas.index = frame;
+        }
+    }
+}
+
+pub struct SpringPlugin;
+
+impl Plugin for SpringPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<SpringBundle>("Spring")
+            // `SpringLaunched` is applied before the player's movement runs in the same tick,
+            // so their steering can't cancel a sideways launch
+            .add_systems(
+                FixedUpdate,
+                (launch_from_springs, apply_deferred)
+                    .chain()
+                    .before(player_movement)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(FixedUpdate, tick_spring_launches.in_set(SimulationSet))
+            .add_systems(Update, animate_springs);
+    }
+}
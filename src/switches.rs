@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::SensorBundle;
+use crate::constants::TILE_SIZE;
+use crate::game_flow::RestartLevel;
+use crate::player::Player;
+use crate::SimulationSet;
+
+/// How faded a switch block is while it can be passed through.
+pub const PASSABLE_BLOCK_ALPHA: f32 = 0.25;
+
+/// The global ON/OFF state that every switch flips and every switch block follows.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Resource)]
+pub struct SwitchState {
+    pub on: bool,
+}
+
+impl Default for SwitchState {
+    fn default() -> Self {
+        Self { on: true }
+    }
+}
+
+/// A merged group of ON or OFF blocks, spawned by the `WallPlugin`.
+/// It is only solid while the `SwitchState` matches it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct SwitchBlock {
+    pub solid_when_on: bool,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct SwitchBlockSprite;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Switch;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SwitchBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    pub switch: Switch,
+}
+
+/// ON blocks are red and OFF blocks are blue, covering the whole merged group.
+pub fn spawn_switch_block_sprites(
+    mut commands: Commands,
+    blocks: Query<(Entity, &SwitchBlock, &Collider), Added<SwitchBlock>>,
+) {
+    for (entity, block, collider) in &blocks {
+        let size = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents() * 2.)
+            .unwrap_or(Vec2::splat(TILE_SIZE as f32));
+        let color = if block.solid_when_on {
+            Color::srgb(0.85, 0.25, 0.25)
+        } else {
+            Color::srgb(0.25, 0.4, 0.85)
+        };
+
+        commands.entity(entity).with_children(|block| {
+            block
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(size),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(SwitchBlockSprite);
+        });
+    }
+}
+
+pub fn flip_switches(
+    mut switch_state: ResMut<SwitchState>,
+    switches: Query<(), With<Switch>>,
+    players: Query<(), With<Player>>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        if let CollisionEvent::Started(collider_a, collider_b, _) = collision {
+            if (switches.contains(*collider_a) && players.contains(*collider_b))
+                || (players.contains(*collider_a) && switches.contains(*collider_b))
+            {
+                switch_state.on = !switch_state.on;
+            }
+        }
+    }
+}
+
+pub fn toggle_switch_blocks(
+    mut commands: Commands,
+    switch_state: Res<SwitchState>,
+    blocks: Query<(Entity, &SwitchBlock, Has<ColliderDisabled>)>,
+) {
+    for (entity, block, disabled) in &blocks {
+        let solid = block.solid_when_on == switch_state.on;
+        if solid && disabled {
+            commands.entity(entity).remove::<ColliderDisabled>();
+        } else if !solid && !disabled {
+            commands.entity(entity).insert(ColliderDisabled);
+        }
+    }
+}
+
+pub fn show_switch_blocks(
+    switch_state: Res<SwitchState>,
+    blocks: Query<&SwitchBlock>,
+    mut sprites: Query<(&Parent, &mut Sprite), With<SwitchBlockSprite>>,
+    added_sprites: Query<(), Added<SwitchBlockSprite>>,
+) {
+    if !switch_state.is_changed() && added_sprites.is_empty() {
+        return;
+    }
+
+    for (parent, mut sprite) in &mut sprites {
+        let Ok(block) = blocks.get(parent.get()) else {
+            continue;
+        };
+
+        let alpha = if block.solid_when_on == switch_state.on {
+            1.
+        } else {
+            PASSABLE_BLOCK_ALPHA
+        };
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+/// Every level starts in the ON state, whether it was restarted or just entered.
+fn reset_switch_state(
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut level_events: EventReader<LevelEvent>,
+    mut switch_state: ResMut<SwitchState>,
+) {
+    let restarted = restart_level_reader.read().count() > 0;
+    let spawned = level_events
+        .read()
+        .any(|event| matches!(event, LevelEvent::Spawned(_)));
+    if restarted || spawned {
+        *switch_state = SwitchState::default();
+    }
+}
+
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SwitchState>()
+            .register_ldtk_entity::<SwitchBundle>("Switch")
+            .add_systems(FixedUpdate, flip_switches.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                toggle_switch_blocks
+                    .after(flip_switches)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, spawn_switch_block_sprites)
+            .add_systems(Update, reset_switch_state)
+            .add_systems(
+                Update,
+                show_switch_blocks
+                    .after(spawn_switch_block_sprites)
+                    .after(reset_switch_state),
+            );
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::SensorBundle;
+use crate::game_flow::{level_index, GameState, RestartLevel};
+use crate::ground_detection::GroundDetection;
+use crate::player::Player;
+use crate::SimulationSet;
+
+/// How long a body that just came through a teleporter is ignored by teleporters.
+pub const TELEPORT_COOLDOWN_SECONDS: f32 = 0.5;
+
+/// Sends dynamic bodies to the teleporter referenced by its `destination` EntityRef field.
+///
+/// The LDtk entity has these fields:
+/// - `destination`: EntityRef, the teleporter to come out of, possibly in another level
+/// - `rotation`: Float, degrees to rotate the velocity by on the way through, 0 keeps it
+///
+/// Only players can go to another level, which is loaded the same way a restart is.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Teleporter {
+    destination: Option<EntityRef>,
+    rotation: f32,
+}
+
+impl From<&EntityInstance> for Teleporter {
+    fn from(entity_instance: &EntityInstance) -> Teleporter {
+        Teleporter {
+            destination: entity_instance
+                .get_entity_ref_field("destination")
+                .ok()
+                .cloned(),
+            rotation: entity_instance
+                .get_float_field("rotation")
+                .copied()
+                .unwrap_or(0.)
+                .to_radians(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct TeleporterBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub teleporter: Teleporter,
+}
+
+#[derive(Clone, Debug, Component)]
+pub struct TeleportCooldown(Timer);
+
+impl Default for TeleportCooldown {
+    fn default() -> Self {
+        TeleportCooldown(Timer::from_seconds(
+            TELEPORT_COOLDOWN_SECONDS,
+            TimerMode::Once,
+        ))
+    }
+}
+
+/// A teleport into another level, waiting for that level to spawn.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct PendingTeleport {
+    arrival: Option<(String, Vec2)>,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn teleport(
+    mut commands: Commands,
+    teleporters: Query<&Teleporter>,
+    destinations: Query<(&EntityIid, &Transform), With<Teleporter>>,
+    mut travellers: Query<
+        (&RigidBody, &mut Transform, &mut Velocity, Has<Player>),
+        (Without<Teleporter>, Without<TeleportCooldown>),
+    >,
+    mut collisions: EventReader<CollisionEvent>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut state: ResMut<GameState>,
+    mut restart_level_writer: EventWriter<RestartLevel>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for collision in collisions.read() {
+        let CollisionEvent::Started(collider_a, collider_b, _) = collision else {
+            continue;
+        };
+
+        for (teleporter_entity, traveller) in [(collider_a, collider_b), (collider_b, collider_a)] {
+            let Ok(teleporter) = teleporters.get(*teleporter_entity) else {
+                continue;
+            };
+            let Some(destination) = &teleporter.destination else {
+                continue;
+            };
+            let Ok((RigidBody::Dynamic, mut transform, mut velocity, is_player)) =
+                travellers.get_mut(*traveller)
+            else {
+                continue;
+            };
+
+            // only bodies that actually travel are turned around
+            let arrival_velocity = Vec2::from_angle(teleporter.rotation).rotate(velocity.linvel);
+
+            let same_level_destination = destinations
+                .iter()
+                .find(|(entity_iid, _)| entity_iid.as_str() == destination.entity_iid);
+
+            if let Some((_, destination_transform)) = same_level_destination {
+                transform.translation.x = destination_transform.translation.x;
+                transform.translation.y = destination_transform.translation.y;
+                velocity.linvel = arrival_velocity;
+                commands
+                    .entity(*traveller)
+                    .insert(TeleportCooldown::default());
+            } else if is_player {
+                let Some(destination_level) = ldtk_project_assets
+                    .get(ldtk_projects.single())
+                    .and_then(|ldtk_project| level_index(ldtk_project, &destination.level_iid))
+                else {
+                    continue;
+                };
+
+                velocity.linvel = arrival_velocity;
+                state.current_level = destination_level;
+                pending_teleport.arrival = Some((destination.entity_iid.clone(), arrival_velocity));
+                restart_level_writer.send(RestartLevel);
+            }
+        }
+    }
+}
+
+/// Moves the players onto their destination once the level they teleported to has spawned,
+/// which is after `restart_level` has put them on its spawn point.
+#[allow(clippy::type_complexity)]
+pub fn arrive_from_other_level(
+    mut commands: Commands,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    destinations: Query<(&EntityIid, &Transform), With<Teleporter>>,
+    mut players: Query<
+        (Entity, &mut Transform, &mut Velocity),
+        (With<Player>, With<GroundDetection>, Without<Teleporter>),
+    >,
+) {
+    let Some((destination_iid, arrival_velocity)) = &pending_teleport.arrival else {
+        return;
+    };
+    let Some((_, destination_transform)) = destinations
+        .iter()
+        .find(|(entity_iid, _)| entity_iid.as_str() == destination_iid)
+    else {
+        return;
+    };
+
+    for (entity, mut transform, mut velocity) in &mut players {
+        transform.translation.x = destination_transform.translation.x;
+        transform.translation.y = destination_transform.translation.y;
+        velocity.linvel = *arrival_velocity;
+        commands.entity(entity).insert(TeleportCooldown::default());
+    }
+    pending_teleport.arrival = None;
+}
+
+pub fn tick_teleport_cooldowns(
+    mut commands: Commands,
+    mut cooldowns: Query<(Entity, &mut TeleportCooldown)>,
+    time: Res<Time>,
+) {
+    for (entity, mut cooldown) in &mut cooldowns {
+        if cooldown.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<TeleportCooldown>();
+        }
+    }
+}
+
+pub struct TeleporterPlugin;
+
+impl Plugin for TeleporterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingTeleport>()
+            .register_ldtk_entity::<TeleporterBundle>("Teleporter")
+            .add_systems(FixedUpdate, teleport.in_set(SimulationSet))
+            .add_systems(Update, arrive_from_other_level)
+            .add_systems(FixedUpdate, tick_teleport_cooldowns.in_set(SimulationSet));
+    }
+}
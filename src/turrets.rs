@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::ColliderBundle;
+use crate::game_flow::RestartLevel;
+use crate::health::{apply_damage, DamageEvent};
+use crate::player::Player;
+use crate::rewind::Rewindable;
+use crate::SimulationSet;
+
+pub const PROJECTILE_RADIUS: f32 = 3.;
+/// Projectiles that never hit anything are returned to the pool after this long.
+pub const PROJECTILE_LIFETIME_SECONDS: f32 = 5.;
+/// Slower fire rates, including zero or negative ones set in LDtk, are raised to this.
+pub const MIN_FIRE_RATE: f32 = 0.01;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum FireMode {
+    /// Fires every time its timer finishes.
+    #[default]
+    Timer,
+    /// Fires on its timer, but only while a player is in its line of fire.
+    LineOfSight,
+}
+
+/// Fires projectiles in a fixed direction.
+///
+/// The LDtk entity has these fields:
+/// - `direction`: Enum, `Up`, `Down`, `Left` or `Right`
+/// - `fire_rate`: Float, shots per second, at least `MIN_FIRE_RATE`
+/// - `mode`: Enum, `Timer` or `LineOfSight`
+/// - `range`: Float, how far a `LineOfSight` turret can see, in pixels
+/// - `projectile_speed`: Float, in pixels per second
+#[derive(Clone, Debug, Default, Component)]
+pub struct Turret {
+    pub direction: Vec2,
+    pub mode: FireMode,
+    pub range: f32,
+    pub projectile_speed: f32,
+    cooldown: Timer,
+}
+
+impl From<&EntityInstance> for Turret {
+    fn from(entity_instance: &EntityInstance) -> Turret {
+        let direction = match entity_instance
+            .get_enum_field("direction")
+            .map(String::as_str)
+        {
+            Ok("Up") => Vec2::Y,
+            Ok("Down") => Vec2::NEG_Y,
+            Ok("Right") => Vec2::X,
+            _ => Vec2::NEG_X,
+        };
+
+        let mode = match entity_instance.get_enum_field("mode").map(String::as_str) {
+            Ok("LineOfSight") => FireMode::LineOfSight,
+            _ => FireMode::Timer,
+        };
+
+        let fire_rate = entity_instance
+            .get_float_field("fire_rate")
+            .copied()
+            .unwrap_or(1.)
+            .max(MIN_FIRE_RATE);
+
+        Turret {
+            direction,
+            mode,
+            range: entity_instance
+                .get_float_field("range")
+                .copied()
+                .unwrap_or(200.),
+            projectile_speed: entity_instance
+                .get_float_field("projectile_speed")
+                .copied()
+                .unwrap_or(150.),
+            cooldown: Timer::from_seconds(1. / fire_rate, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct TurretBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub turret: Turret,
+    pub rewindable: Rewindable,
+}
+
+/// Projectiles are never despawned. Inactive ones are hidden with their collider disabled,
+/// waiting to be fired again.
+#[derive(Clone, Debug, Component)]
+pub struct Projectile {
+    pub active: bool,
+    /// The turret that fired it, which it starts out inside of
+    owner: Entity,
+    lifetime: Timer,
+}
+
+fn deactivate_projectile(
+    commands: &mut Commands,
+    entity: Entity,
+    projectile: &mut Projectile,
+    velocity: &mut Velocity,
+    visibility: &mut Visibility,
+) {
+    projectile.active = false;
+    *velocity = Velocity::zero();
+    *visibility = Visibility::Hidden;
+    commands.entity(entity).insert(ColliderDisabled);
+}
+
+/// Projectiles live outside of the level hierarchy, so they survive level changes and stay pooled.
+pub fn fire_turrets(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    mut turrets: Query<(Entity, &mut Turret, &GlobalTransform)>,
+    mut projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &mut Transform,
+        &mut Velocity,
+        &mut Visibility,
+    )>,
+    players: Query<(), With<Player>>,
+    time: Res<Time>,
+) {
+    for (turret_entity, mut turret, turret_transform) in &mut turrets {
+        if !turret.cooldown.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let origin = turret_transform.translation().truncate();
+        if turret.mode == FireMode::LineOfSight {
+            let sees_player = rapier_context
+                .cast_ray(
+                    origin,
+                    turret.direction,
+                    turret.range,
+                    true,
+                    QueryFilter::default()
+                        .exclude_sensors()
+                        .exclude_collider(turret_entity),
+                )
+                .is_some_and(|(entity, _)| players.contains(entity));
+            if !sees_player {
+                continue;
+            }
+        }
+
+        let translation = origin.extend(turret_transform.translation().z + 1.);
+        let linvel = turret.direction * turret.projectile_speed;
+
+        let pooled = projectiles
+            .iter_mut()
+            .find(|(_, projectile, ..)| !projectile.active);
+        if let Some((entity, mut projectile, mut transform, mut velocity, mut visibility)) = pooled
+        {
+            projectile.active = true;
+            projectile.owner = turret_entity;
+            projectile.lifetime.reset();
+            transform.translation = translation;
+            velocity.linvel = linvel;
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<ColliderDisabled>();
+        } else {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::srgb(0.95, 0.5, 0.2),
+                        custom_size: Some(Vec2::splat(PROJECTILE_RADIUS * 2.)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(translation),
+                    ..Default::default()
+                })
+                .insert(RigidBody::KinematicVelocityBased)
+                .insert(Velocity::linear(linvel))
+                .insert(Collider::ball(PROJECTILE_RADIUS))
+                .insert(Sensor)
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                // kinematic bodies don't report touching the fixed walls by default
+                .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC)
+                .insert(Projectile {
+                    active: true,
+                    owner: turret_entity,
+                    lifetime: Timer::from_seconds(PROJECTILE_LIFETIME_SECONDS, TimerMode::Once),
+                })
+                .insert(Rewindable);
+        }
+    }
+}
+
+/// Hitting a player hurts them. Hitting anything else solid returns the projectile to the pool.
+pub fn projectile_hits(
+    mut commands: Commands,
+    mut projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &GlobalTransform,
+        &mut Velocity,
+        &mut Visibility,
+    )>,
+    players: Query<(), With<Player>>,
+    solids: Query<(), (With<Collider>, Without<Sensor>)>,
+    mut collisions: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for collision in collisions.read() {
+        let CollisionEvent::Started(collider_a, collider_b, _) = collision else {
+            continue;
+        };
+
+        for (projectile_entity, other) in [(collider_a, collider_b), (collider_b, collider_a)] {
+            let Ok((entity, mut projectile, transform, mut velocity, mut visibility)) =
+                projectiles.get_mut(*projectile_entity)
+            else {
+                continue;
+            };
+            if !projectile.active || projectile.owner == *other || !solids.contains(*other) {
+                continue;
+            }
+
+            if players.contains(*other) {
+                damage_events.send(DamageEvent {
+                    target: *other,
+                    amount: 1,
+                    source: transform.translation().truncate(),
+                });
+            }
+            deactivate_projectile(
+                &mut commands,
+                entity,
+                &mut projectile,
+                &mut velocity,
+                &mut visibility,
+            );
+        }
+    }
+}
+
+pub fn expire_projectiles(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Velocity, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut projectile, mut velocity, mut visibility) in &mut projectiles {
+        if projectile.active && projectile.lifetime.tick(time.delta()).finished() {
+            deactivate_projectile(
+                &mut commands,
+                entity,
+                &mut projectile,
+                &mut velocity,
+                &mut visibility,
+            );
+        }
+    }
+}
+
+fn reset_turrets(
+    mut commands: Commands,
+    mut restart_level_reader: EventReader<RestartLevel>,
+    mut turrets: Query<&mut Turret>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Velocity, &mut Visibility)>,
+) {
+    if restart_level_reader.read().count() == 0 {
+        return;
+    }
+
+    for mut turret in &mut turrets {
+        turret.cooldown.reset();
+    }
+    for (entity, mut projectile, mut velocity, mut visibility) in &mut projectiles {
+        deactivate_projectile(
+            &mut commands,
+            entity,
+            &mut projectile,
+            &mut velocity,
+            &mut visibility,
+        );
+    }
+}
+
+pub struct TurretPlugin;
+
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<TurretBundle>("Turret")
+            .add_systems(FixedUpdate, fire_turrets.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                projectile_hits.before(apply_damage).in_set(SimulationSet),
+            )
+            .add_systems(FixedUpdate, expire_projectiles.in_set(SimulationSet))
+            .add_systems(Update, reset_turrets);
+    }
+}
@@ -1,3 +1,8 @@
 pub fn is_almost_zero(x: f32) -> bool {
     x.abs() < f32::EPSILON
 }
+
+/// Moves `current` towards `target` by at most `max_delta`, without overshooting.
+pub fn move_towards(current: f32, target: f32, max_delta: f32) -> f32 {
+    current + (target - current).clamp(-max_delta, max_delta)
+}
@@ -3,17 +3,221 @@ use std::collections::HashSet;
 use bevy::{prelude::*, utils::HashMap};
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::constants::IntGridValues;
+use crate::colliders::SensorBundle;
+use crate::constants::{IntGridValues, TILE_SIZE};
+use crate::conveyors::{ConveyorBelt, ConveyorDirection};
+use crate::crumble::CrumbleTile;
+use crate::one_way_platforms::OneWayPlatform;
+use crate::rewind::Rewindable;
+use crate::slopes::Slope;
+use crate::switches::SwitchBlock;
+use crate::water::Water;
 use bevy_rapier2d::prelude::*;
 
+/// The material of a solid wall, which decides how bodies move on it.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Component)]
+pub enum SurfaceMaterial {
+    #[default]
+    Dirt,
+    Stone,
+    Ice,
+}
+
+impl SurfaceMaterial {
+    pub fn friction(&self) -> f32 {
+        match self {
+            SurfaceMaterial::Dirt => 1.0,
+            SurfaceMaterial::Stone => 0.8,
+            SurfaceMaterial::Ice => 0.02,
+        }
+    }
+
+    pub fn restitution(&self) -> f32 {
+        match self {
+            SurfaceMaterial::Dirt => 0.0,
+            SurfaceMaterial::Stone => 0.1,
+            SurfaceMaterial::Ice => 0.0,
+        }
+    }
+
+    /// How quickly, in pixels per second squared, a walker standing on this material
+    /// reaches the speed they want. `None` means they reach it instantly.
+    pub fn ground_acceleration(&self) -> Option<f32> {
+        match self {
+            SurfaceMaterial::Dirt | SurfaceMaterial::Stone => None,
+            SurfaceMaterial::Ice => Some(300.),
+        }
+    }
+}
+
+/// Walls of different kinds are never merged into the same collider.
+/// Water is merged the same way, but into sensor volumes instead of solid colliders.
+/// Slopes are not merged at all.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum WallKind {
+    Solid(SurfaceMaterial),
+    OneWay,
+    Water,
+    Slope(Slope),
+    /// ON or OFF blocks, toggled at runtime by the switch state
+    SwitchBlock {
+        solid_when_on: bool,
+    },
+    Conveyor(ConveyorDirection),
+    /// Never merged, so that every tile can fall away on its own
+    Crumble,
+}
+
+impl Default for WallKind {
+    fn default() -> Self {
+        WallKind::Solid(SurfaceMaterial::default())
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
-pub struct Wall;
+pub struct Wall {
+    pub kind: WallKind,
+}
+
+impl From<IntGridCell> for Wall {
+    fn from(int_grid_cell: IntGridCell) -> Wall {
+        let kind = match IntGridValues::from(int_grid_cell.value) {
+            IntGridValues::OneWay => WallKind::OneWay,
+            IntGridValues::Water => WallKind::Water,
+            IntGridValues::Stone => WallKind::Solid(SurfaceMaterial::Stone),
+            IntGridValues::Ice => WallKind::Solid(SurfaceMaterial::Ice),
+            IntGridValues::OnBlock => WallKind::SwitchBlock {
+                solid_when_on: true,
+            },
+            IntGridValues::OffBlock => WallKind::SwitchBlock {
+                solid_when_on: false,
+            },
+            IntGridValues::ConveyorLeft => WallKind::Conveyor(ConveyorDirection::Left),
+            IntGridValues::ConveyorRight => WallKind::Conveyor(ConveyorDirection::Right),
+            IntGridValues::Crumble => WallKind::Crumble,
+            IntGridValues::SlopeUpRight => WallKind::Slope(Slope::UpRight),
+            IntGridValues::SlopeUpLeft => WallKind::Slope(Slope::UpLeft),
+            IntGridValues::GentleSlopeUpRightLow => WallKind::Slope(Slope::GentleUpRightLow),
+            IntGridValues::GentleSlopeUpRightHigh => WallKind::Slope(Slope::GentleUpRightHigh),
+            IntGridValues::GentleSlopeUpLeftLow => WallKind::Slope(Slope::GentleUpLeftLow),
+            IntGridValues::GentleSlopeUpLeftHigh => WallKind::Slope(Slope::GentleUpLeftHigh),
+            _ => WallKind::Solid(SurfaceMaterial::Dirt),
+        };
+        Wall { kind }
+    }
+}
 
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct WallBundle {
+    #[from_int_grid_cell]
     wall: Wall,
 }
 
+/// Represents a wide wall that is 1 tile tall
+/// Used to spawn wall collisions
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+/// A simple rectangle type representing a wall of any size
+struct Rect {
+    left: i32,
+    right: i32,
+    top: i32,
+    bottom: i32,
+}
+
+impl From<GridCoords> for Rect {
+    fn from(grid_coords: GridCoords) -> Rect {
+        Rect {
+            left: grid_coords.x,
+            right: grid_coords.x,
+            top: grid_coords.y,
+            bottom: grid_coords.y,
+        }
+    }
+}
+
+impl Rect {
+    fn collider(&self, grid_size: f32) -> Collider {
+        Collider::cuboid(
+            (self.right as f32 - self.left as f32 + 1.) * grid_size / 2.,
+            (self.top as f32 - self.bottom as f32 + 1.) * grid_size / 2.,
+        )
+    }
+
+    fn transform(&self, grid_size: f32) -> Transform {
+        Transform::from_xyz(
+            (self.left + self.right + 1) as f32 * grid_size / 2.,
+            (self.bottom + self.top + 1) as f32 * grid_size / 2.,
+            0.,
+        )
+    }
+}
+
+/// Combines the given wall tiles into as few rectangles as possible.
+fn merge_wall_rects(level_walls: &HashSet<GridCoords>, width: i32, height: i32) -> Vec<Rect> {
+    // combine wall tiles into flat "plates" in each individual row
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+
+    for y in 0..height {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width so the algorithm "terminates" plates that touch the right edge
+        for x in 0..width + 1 {
+            match (plate_start, level_walls.contains(&GridCoords { x, y })) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: x - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+
+        plate_stack.push(row_plates);
+    }
+
+    // combine "plates" into rectangles across multiple rows
+    let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
+    let mut prev_row: Vec<Plate> = Vec::new();
+    let mut wall_rects: Vec<Rect> = Vec::new();
+
+    // an extra empty row so the algorithm "finishes" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (y, current_row) in plate_stack.into_iter().enumerate() {
+        for prev_plate in &prev_row {
+            if !current_row.contains(prev_plate) {
+                // remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_plate) {
+                    wall_rects.push(rect);
+                }
+            }
+        }
+        for plate in &current_row {
+            rect_builder
+                .entry(plate.clone())
+                .and_modify(|e| e.top += 1)
+                .or_insert(Rect {
+                    bottom: y as i32,
+                    top: y as i32,
+                    left: plate.left,
+                    right: plate.right,
+                });
+        }
+        prev_row = current_row;
+    }
+
+    wall_rects
+}
+
 /// Spawns heron collisions for the walls of a level
 ///
 /// You could just insert a ColliderBundle into the WallBundle,
@@ -32,28 +236,12 @@ pub struct WallBundle {
 /// 4. spawn colliders for each rectangle
 pub fn spawn_wall_collision(
     mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<Wall>>,
+    wall_query: Query<(&GridCoords, &Wall, &Parent), Added<Wall>>,
     parent_query: Query<&Parent, Without<Wall>>,
     level_query: Query<(Entity, &LevelIid)>,
     ldtk_projects: Query<&Handle<LdtkProject>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
-    }
-
-    /// A simple rectangle type representing a wall of any size
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
-    }
-
     // Consider where the walls are
     // storing them as GridCoords in a HashSet for quick, easy lookup
     //
@@ -61,9 +249,12 @@ pub fn spawn_wall_collision(
     // This has two consequences in the resulting collision entities:
     // 1. it forces the walls to be split along level boundaries
     // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
+    //
+    // Within a level, walls are further grouped by kind so that each kind is merged separately.
+    let mut level_to_wall_locations: HashMap<Entity, HashMap<WallKind, HashSet<GridCoords>>> =
+        HashMap::new();
 
-    wall_query.iter().for_each(|(&grid_coords, parent)| {
+    wall_query.iter().for_each(|(&grid_coords, wall, parent)| {
         // An intgrid tile's direct parent will be a layer entity, not the level entity
         // To get the level entity, you need the tile's grandparent.
         // This is where parent_query comes in.
@@ -71,6 +262,8 @@ pub fn spawn_wall_collision(
             level_to_wall_locations
                 .entry(grandparent.get())
                 .or_default()
+                .entry(wall.kind)
+                .or_default()
                 .insert(grid_coords);
         }
     });
@@ -94,88 +287,98 @@ pub fn spawn_wall_collision(
                     ..
                 } = level.layer_instances()[0];
 
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
-
-                    plate_stack.push(row_plates);
-                }
-
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
-                        }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| e.top += 1)
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
-                            });
-                    }
-                    prev_row = current_row;
-                }
-
                 commands.entity(level_entity).with_children(|level| {
                     // Spawn colliders for every rectangle..
                     // Making the collider a child of the level serves two purposes:
                     // 1. Adjusts the transforms to be relative to the level for free
                     // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                            ))
-                            .insert(RigidBody::Fixed)
-                            .insert(Friction::new(1.0))
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(GlobalTransform::default());
+                    for (kind, locations) in level_walls {
+                        let wall_rects: Vec<Rect> = match kind {
+                            // Slopes are not rectangles and crumble tiles are removed one by one,
+                            // so every one of their tiles is spawned on its own
+                            WallKind::Slope(_) | WallKind::Crumble => locations
+                                .iter()
+                                .map(|&grid_coords| Rect::from(grid_coords))
+                                .collect(),
+                            _ => merge_wall_rects(locations, width, height),
+                        };
+
+                        for wall_rect in wall_rects {
+                            let collider = match kind {
+                                WallKind::Slope(slope) => slope.collider(grid_size as f32 / 2.),
+                                _ => wall_rect.collider(grid_size as f32),
+                            };
+
+                            let mut wall = level.spawn_empty();
+                            wall.insert(wall_rect.transform(grid_size as f32))
+                                .insert(GlobalTransform::default());
+
+                            match kind {
+                                WallKind::Solid(material) => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(material.friction()))
+                                        .insert(Restitution::coefficient(material.restitution()))
+                                        .insert(*material)
+                                        .insert(VisibilityBundle::default());
+                                }
+                                WallKind::OneWay => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(1.0))
+                                        .insert(SurfaceMaterial::Dirt)
+                                        .insert(OneWayPlatform)
+                                        .insert(ActiveHooks::MODIFY_SOLVER_CONTACTS)
+                                        .insert(VisibilityBundle::default());
+                                }
+                                WallKind::Slope(slope) => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(SurfaceMaterial::Dirt.friction()))
+                                        .insert(SurfaceMaterial::Dirt)
+                                        .insert(*slope)
+                                        .insert(VisibilityBundle::default());
+                                }
+                                WallKind::SwitchBlock { solid_when_on } => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(1.0))
+                                        .insert(SurfaceMaterial::Dirt)
+                                        .insert(SwitchBlock {
+                                            solid_when_on: *solid_when_on,
+                                        })
+                                        .insert(VisibilityBundle::default())
+                                        .insert(Rewindable);
+                                }
+                                WallKind::Conveyor(direction) => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(1.0))
+                                        .insert(SurfaceMaterial::Dirt)
+                                        .insert(ConveyorBelt::from(*direction))
+                                        .insert(VisibilityBundle::default());
+                                }
+                                WallKind::Crumble => {
+                                    wall.insert(collider)
+                                        .insert(RigidBody::Fixed)
+                                        .insert(Friction::new(1.0))
+                                        .insert(SurfaceMaterial::Dirt)
+                                        .insert(CrumbleTile::default())
+                                        .insert(VisibilityBundle::default())
+                                        .insert(Rewindable);
+                                }
+                                WallKind::Water => {
+                                    wall.insert(SensorBundle {
+                                        collider,
+                                        sensor: Sensor,
+                                        active_events: ActiveEvents::COLLISION_EVENTS,
+                                        ..Default::default()
+                                    })
+                                    .insert(Water)
+                                    .insert(VisibilityBundle::default());
+                                }
+                            }
+                        }
                     }
                 });
             }
@@ -183,6 +386,34 @@ pub fn spawn_wall_collision(
     }
 }
 
+/// Ice is on the IntGrid layer without tiles, so icy walls are drawn as pale blue blocks.
+pub fn spawn_ice_sprites(
+    mut commands: Commands,
+    walls: Query<(Entity, &SurfaceMaterial, &Collider), Added<SurfaceMaterial>>,
+) {
+    for (entity, material, collider) in &walls {
+        if *material != SurfaceMaterial::Ice {
+            continue;
+        }
+
+        let size = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents() * 2.)
+            .unwrap_or(Vec2::splat(TILE_SIZE as f32));
+
+        commands.entity(entity).with_children(|wall| {
+            wall.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(0.66, 0.89, 0.94),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        });
+    }
+}
+
 /// Plugin which spawns walls on appropriate LDtk int cells,
 /// then merges them together to reduce physics load.
 pub struct WallPlugin;
@@ -190,7 +421,22 @@ pub struct WallPlugin;
 impl Plugin for WallPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, spawn_wall_collision)
+            .add_systems(Update, spawn_ice_sprites)
             .register_ldtk_int_cell::<WallBundle>(IntGridValues::Dirt as i32)
-            .register_ldtk_int_cell::<WallBundle>(IntGridValues::Stone as i32);
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::Stone as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::Ice as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::OneWay as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::Water as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::SlopeUpRight as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::SlopeUpLeft as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::GentleSlopeUpRightLow as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::GentleSlopeUpRightHigh as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::GentleSlopeUpLeftLow as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::GentleSlopeUpLeftHigh as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::OnBlock as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::OffBlock as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::ConveyorLeft as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::ConveyorRight as i32)
+            .register_ldtk_int_cell::<WallBundle>(IntGridValues::Crumble as i32);
     }
 }
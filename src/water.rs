@@ -0,0 +1,140 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::AudioEvent;
+use crate::climbing::{ignore_gravity_if_climbing, Climber};
+use crate::constants::TILE_SIZE;
+use crate::SimulationSet;
+
+pub const SWIM_GRAVITY_SCALE: f32 = 0.3;
+pub const SWIM_LINEAR_DAMPING: f32 = 3.0;
+pub const SWIM_SPEED_FACTOR: f32 = 0.6;
+pub const SWIM_STROKE_VELOCITY: f32 = 200.;
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Swimmer {
+    pub swimming: bool,
+    pub intersecting_water: HashSet<Entity>,
+}
+
+/// Marks a merged water volume, spawned by the `WallPlugin`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Water;
+
+/// The IntGrid layer has no tiles of its own, so every volume is drawn as see-through water.
+pub fn spawn_water_sprites(
+    mut commands: Commands,
+    volumes: Query<(Entity, &Collider), Added<Water>>,
+) {
+    for (entity, collider) in &volumes {
+        let size = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents() * 2.)
+            .unwrap_or(Vec2::splat(TILE_SIZE as f32));
+
+        commands.entity(entity).with_children(|water| {
+            water.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgba(0.29, 0.56, 0.84, 0.5),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        });
+    }
+}
+
+pub fn detect_water(
+    mut swimmers: Query<&mut Swimmer>,
+    water: Query<Entity, With<Water>>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        match collision {
+            CollisionEvent::Started(collider_a, collider_b, _) => {
+                if let (Ok(mut swimmer), Ok(water)) =
+                    (swimmers.get_mut(*collider_a), water.get(*collider_b))
+                {
+                    swimmer.intersecting_water.insert(water);
+                }
+                if let (Ok(mut swimmer), Ok(water)) =
+                    (swimmers.get_mut(*collider_b), water.get(*collider_a))
+                {
+                    swimmer.intersecting_water.insert(water);
+                };
+            }
+            CollisionEvent::Stopped(collider_a, collider_b, _) => {
+                if let (Ok(mut swimmer), Ok(water)) =
+                    (swimmers.get_mut(*collider_a), water.get(*collider_b))
+                {
+                    swimmer.intersecting_water.remove(&water);
+                }
+
+                if let (Ok(mut swimmer), Ok(water)) =
+                    (swimmers.get_mut(*collider_b), water.get(*collider_a))
+                {
+                    swimmer.intersecting_water.remove(&water);
+                }
+            }
+        }
+    }
+}
+
+/// There is a single splash sound, played both when diving in and when climbing out.
+pub fn update_swimming(
+    mut swimmers: Query<&mut Swimmer, Changed<Swimmer>>,
+    mut audio_event: EventWriter<AudioEvent>,
+) {
+    for mut swimmer in &mut swimmers {
+        let in_water = !swimmer.intersecting_water.is_empty();
+        if swimmer.swimming != in_water {
+            swimmer.swimming = in_water;
+            audio_event.send(AudioEvent::Splash);
+        }
+    }
+}
+
+/// Climbing takes precedence over swimming, so this runs after the climbing gravity is set.
+#[allow(clippy::type_complexity)]
+pub fn apply_water_physics(
+    mut query: Query<
+        (&Swimmer, &Climber, &mut GravityScale, &mut Damping),
+        Or<(Changed<Swimmer>, Changed<Climber>)>,
+    >,
+) {
+    for (swimmer, climber, mut gravity_scale, mut damping) in &mut query {
+        if !climber.climbing {
+            gravity_scale.0 = if swimmer.swimming {
+                SWIM_GRAVITY_SCALE
+            } else {
+                1.0
+            };
+        }
+        damping.linear_damping = if swimmer.swimming {
+            SWIM_LINEAR_DAMPING
+        } else {
+            0.0
+        };
+    }
+}
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_water_sprites)
+            .add_systems(FixedUpdate, detect_water.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                update_swimming.after(detect_water).in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                apply_water_physics
+                    .after(update_swimming)
+                    .after(ignore_gravity_if_climbing)
+                    .in_set(SimulationSet),
+            );
+    }
+}
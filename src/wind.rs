@@ -0,0 +1,248 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::colliders::SensorBundle;
+use crate::game_flow::RngSeed;
+use crate::player::{player_movement, Player};
+use crate::SimulationSet;
+
+/// How quickly a player's drift settles, per second. A wind blows a player standing in it
+/// up to `strength / WIND_DRIFT_DAMPING`, which is slow enough for them to walk against it.
+pub const WIND_DRIFT_DAMPING: f32 = 5.;
+pub const WIND_PARTICLE_SPEED: f32 = 150.;
+pub const WIND_PARTICLES_PER_SECOND: f32 = 12.;
+
+/// Pushes the bodies inside it.
+///
+/// The LDtk entity has these fields:
+/// - `direction`: Enum, `Up`, `Down`, `Left` or `Right`
+/// - `strength`: Float, the acceleration given to bodies inside, in pixels per second squared
+#[derive(Clone, Debug, Default, Component)]
+pub struct WindZone {
+    pub direction: Vec2,
+    pub strength: f32,
+    half_extents: Vec2,
+    particle_timer: Timer,
+    particle_seed: u32,
+}
+
+impl From<&EntityInstance> for WindZone {
+    fn from(entity_instance: &EntityInstance) -> WindZone {
+        let direction = match entity_instance
+            .get_enum_field("direction")
+            .map(String::as_str)
+        {
+            Ok("Up") => Vec2::Y,
+            Ok("Down") => Vec2::NEG_Y,
+            Ok("Left") => Vec2::NEG_X,
+            _ => Vec2::X,
+        };
+
+        WindZone {
+            direction,
+            strength: entity_instance
+                .get_float_field("strength")
+                .copied()
+                .unwrap_or(800.),
+            half_extents: Vec2::new(
+                entity_instance.width as f32 / 2.,
+                entity_instance.height as f32 / 2.,
+            ),
+            particle_timer: Timer::from_seconds(
+                1. / WIND_PARTICLES_PER_SECOND,
+                TimerMode::Repeating,
+            ),
+            particle_seed: entity_instance.iid.bytes().fold(1, |seed, byte| {
+                seed.wrapping_mul(31).wrapping_add(byte as u32)
+            }) | 1,
+        }
+    }
+}
+
+impl WindZone {
+    pub fn acceleration(&self) -> Vec2 {
+        self.direction * self.strength
+    }
+
+    /// A cheap pseudo-random number in -1..1, good enough to scatter particles.
+    fn next_random(&mut self) -> f32 {
+        self.particle_seed ^= self.particle_seed << 13;
+        self.particle_seed ^= self.particle_seed >> 17;
+        self.particle_seed ^= self.particle_seed << 5;
+        self.particle_seed as f32 / u32::MAX as f32 * 2. - 1.
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct WindZoneBundle {
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub wind_zone: WindZone,
+}
+
+/// Bodies that wind zones can push.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct Windswept {
+    pub intersecting_zones: HashSet<Entity>,
+    /// The horizontal speed a player has been blown up to, which their movement adds to their own
+    pub drift: f32,
+}
+
+#[derive(Clone, Default, Bundle)]
+pub struct WindsweptBundle {
+    pub windswept: Windswept,
+    pub external_force: ExternalForce,
+    pub mass_properties: ReadMassProperties,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct WindParticle {
+    velocity: Vec2,
+}
+
+pub fn detect_wind_zones(
+    mut bodies: Query<&mut Windswept>,
+    zones: Query<(), With<WindZone>>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for collision in collisions.read() {
+        match collision {
+            CollisionEvent::Started(collider_a, collider_b, _) => {
+                for (body, zone) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if !zones.contains(*zone) {
+                        continue;
+                    }
+                    if let Ok(mut windswept) = bodies.get_mut(*body) {
+                        windswept.intersecting_zones.insert(*zone);
+                    }
+                }
+            }
+            CollisionEvent::Stopped(collider_a, collider_b, _) => {
+                for (body, zone) in [(collider_a, collider_b), (collider_b, collider_a)] {
+                    if let Ok(mut windswept) = bodies.get_mut(*body) {
+                        windswept.intersecting_zones.remove(zone);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Forces are scaled by mass, so every body in a zone is accelerated the same.
+///
+/// Players set their own horizontal speed every tick, which would undo a horizontal force,
+/// so the wind builds up their `drift` instead.
+pub fn apply_wind(
+    mut bodies: Query<(
+        &mut Windswept,
+        &ReadMassProperties,
+        &mut ExternalForce,
+        Has<Player>,
+    )>,
+    zones: Query<&WindZone>,
+    time: Res<Time>,
+) {
+    for (mut windswept, mass_properties, mut external_force, is_player) in &mut bodies {
+        let mut acceleration: Vec2 = zones
+            .iter_many(&windswept.intersecting_zones)
+            .map(WindZone::acceleration)
+            .sum();
+
+        if is_player {
+            let drift = windswept.drift
+                + (acceleration.x - windswept.drift * WIND_DRIFT_DAMPING) * time.delta_seconds();
+            // out of the wind, the drift settles all the way back to standing still
+            windswept.drift = if acceleration.x == 0. && drift.abs() < 1. {
+                0.
+            } else {
+                drift
+            };
+            acceleration.x = 0.;
+        }
+
+        let force = acceleration * mass_properties.get().mass;
+        if external_force.force != force {
+            external_force.force = force;
+        }
+    }
+}
+
+/// Mixes the game's seed into new zones, which are otherwise seeded by their iid.
+fn seed_wind_zones(rng_seed: Res<RngSeed>, mut zones: Query<&mut WindZone, Added<WindZone>>) {
+    for mut zone in &mut zones {
+        zone.particle_seed = (zone.particle_seed ^ rng_seed.0) | 1;
+    }
+}
+
+pub fn spawn_wind_particles(
+    mut commands: Commands,
+    mut zones: Query<(Entity, &mut WindZone)>,
+    time: Res<Time>,
+) {
+    for (entity, mut zone) in &mut zones {
+        zone.particle_timer.tick(time.delta());
+        for _ in 0..zone.particle_timer.times_finished_this_tick() {
+            let position = Vec2::new(zone.next_random(), zone.next_random()) * zone.half_extents;
+            let velocity = zone.direction * WIND_PARTICLE_SPEED;
+
+            commands.entity(entity).with_children(|zone| {
+                zone.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::srgba(1., 1., 1., 0.5),
+                        // streaks are stretched along the flow
+                        custom_size: Some(if velocity.x != 0. {
+                            Vec2::new(6., 1.)
+                        } else {
+                            Vec2::new(1., 6.)
+                        }),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(position.extend(1.)),
+                    ..Default::default()
+                })
+                .insert(WindParticle { velocity });
+            });
+        }
+    }
+}
+
+/// Particles live in their zone's space and are removed once they blow out of it.
+pub fn move_wind_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &WindParticle, &Parent, &mut Transform)>,
+    zones: Query<&WindZone>,
+    time: Res<Time>,
+) {
+    for (entity, particle, parent, mut transform) in &mut particles {
+        let Ok(zone) = zones.get(parent.get()) else {
+            continue;
+        };
+
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        let position = transform.translation.truncate();
+        if position.x.abs() > zone.half_extents.x || position.y.abs() > zone.half_extents.y {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<WindZoneBundle>("WindZone")
+            .add_systems(FixedUpdate, detect_wind_zones.in_set(SimulationSet))
+            .add_systems(
+                FixedUpdate,
+                apply_wind
+                    .after(detect_wind_zones)
+                    .before(player_movement)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, seed_wind_zones)
+            .add_systems(Update, spawn_wind_particles.after(seed_wind_zones))
+            .add_systems(Update, move_wind_particles);
+    }
+}